@@ -0,0 +1,74 @@
+use crate::vector::*;
+use crate::ray::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct BoundingBox {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+impl BoundingBox {
+    // contains nothing, the union with any other box yields that box
+    pub fn empty() -> BoundingBox {
+	BoundingBox {
+	    min: Vector { x:  f32::INFINITY, y:  f32::INFINITY, z:  f32::INFINITY },
+	    max: Vector { x: -f32::INFINITY, y: -f32::INFINITY, z: -f32::INFINITY },
+	}
+    }
+
+    pub fn include(self, point: Vector) -> BoundingBox {
+	BoundingBox {
+	    min: Vector { x: self.min.x.min(point.x), y: self.min.y.min(point.y), z: self.min.z.min(point.z) },
+	    max: Vector { x: self.max.x.max(point.x), y: self.max.y.max(point.y), z: self.max.z.max(point.z) },
+	}
+    }
+
+    pub fn union(self, other: BoundingBox) -> BoundingBox {
+	self.include(other.min).include(other.max)
+    }
+
+    // false for boxes that reach out to infinity, as well as for empty ones
+    pub fn is_finite(self) -> bool {
+	self.min.x.is_finite() && self.min.y.is_finite() && self.min.z.is_finite()
+	    && self.max.x.is_finite() && self.max.y.is_finite() && self.max.z.is_finite()
+    }
+
+    pub fn centroid(self) -> Vector {
+	(self.min + self.max) * 0.5f32
+    }
+
+    pub fn extent(self) -> Vector {
+	self.max - self.min
+    }
+
+    pub fn surface_area(self) -> f32 {
+	let extent = self.extent();
+	if extent.x < 0f32 || extent.y < 0f32 || extent.z < 0f32 {
+	    return 0f32;
+	}
+
+	2f32 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    // slab test, returns the distance at which the ray enters the box, if it does so within the range of the ray
+    // and before max_depth
+    // inverse_direction is passed in so it can be computed once per ray instead of once per box
+    pub fn intersect(self, ray: Ray, inverse_direction: Vector, max_depth: f32) -> Option<f32> {
+	let tx1 = (self.min.x - ray.origin.x) * inverse_direction.x;
+	let tx2 = (self.max.x - ray.origin.x) * inverse_direction.x;
+	let ty1 = (self.min.y - ray.origin.y) * inverse_direction.y;
+	let ty2 = (self.max.y - ray.origin.y) * inverse_direction.y;
+	let tz1 = (self.min.z - ray.origin.z) * inverse_direction.z;
+	let tz2 = (self.max.z - ray.origin.z) * inverse_direction.z;
+
+	// the exit is moved out by the rounding error of the distances, so rays do not slip past the edges of boxes
+	let t_enter = tx1.min(tx2).max(ty1.min(ty2)).max(tz1.min(tz2)).max(ray.t_min);
+	let t_exit  = (tx1.max(tx2).min(ty1.max(ty2)).min(tz1.max(tz2)) * (1f32 + 2f32 * rounding_error(3))).min(max_depth).min(ray.t_max);
+
+	if t_enter <= t_exit {
+	    Some(t_enter)
+	} else {
+	    None
+	}
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::Debug;
+use std::f32::consts::PI;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::surface_element::*;
+
+// how a surface scatters light, in a local frame with the shading normal along z (see Frame),
+// wo is the direction the light leaves in and wi the direction it arrives from, both pointing away from the surface
+pub trait Bsdf: Debug + Send + Sync {
+    // the ratio of the light leaving in wo to the light arriving from wi, without the cosine factor
+    fn eval(&self, surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> Color;
+
+    // picks wi given wo, using the numbers in u which are uniform in [0, 1),
+    // None when no light leaves in wo or the picked direction ends up on the wrong side of the surface
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, u: [f32; 3]) -> Option<BsdfSample>;
+
+    // density with respect to solid angle of sample picking wi
+    fn pdf(&self, surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> f32;
+
+    // whether light is only scattered into a few discrete directions, in which case eval and pdf are zero
+    // and the directions can only be found by sample
+    fn is_specular(&self) -> bool {
+	false
+    }
+
+    // surfaces that light can pass through can be seen from both sides, all others only from the front
+    fn is_two_sided(&self) -> bool {
+	false
+    }
+
+    // fraction of the light that makes it through the given distance inside
+    fn transmittance(&self, _distance: f32) -> Color {
+	WHITE
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BsdfSample {
+    pub wi: Vector,
+    pub weight: Color, // eval times the cosine of wi divided by pdf, what the light arriving from wi is multiplied by
+    pub pdf: f32, // for specular bsdfs the probability of picking this particular direction
+}
+
+// whether both directions are on the same side of the surface, so the light is reflected rather than transmitted
+pub fn same_hemisphere(wo: Vector, wi: Vector) -> bool {
+    wo.z * wi.z > 0f32
+}
+
+// maps u1 and u2 to a direction above the surface with a density of cos theta / PI
+pub fn cosine_weighed_direction(u1: f32, u2: f32) -> Vector {
+    let r = u1.sqrt();
+    let phi = 2f32 * PI * u2;
+
+    Vector {
+	x: r * phi.cos(),
+	y: r * phi.sin(),
+	z: (1f32 - u1).max(0f32).sqrt(),
+    }
+}
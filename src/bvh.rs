@@ -0,0 +1,314 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::bounding_box::*;
+
+// number of buckets the centroids are sorted into when evaluating the surface area heuristic
+const NUM_BUCKETS: usize = 12;
+
+// relative costs used by the surface area heuristic
+const TRAVERSAL_COST: f32 = 1f32;
+const INTERSECTION_COST: f32 = 2f32;
+
+const MAX_PRIMITIVES_IN_LEAF: usize = 4;
+
+#[derive(Copy, Clone, Debug)]
+struct BvhNode {
+    bounds: BoundingBox,
+    // for a leaf: index of the first primitive in Bvh::indices
+    // for an interior node: index of the second child, the first child directly follows its parent
+    offset: usize,
+    // number of primitives, zero for interior nodes
+    count: usize,
+    // axis along which the children were split, used to visit the nearest child first
+    axis: usize,
+}
+
+// bounding volume hierarchy, only stores indices into a list of primitives that is kept elsewhere
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bounds: BoundingBox,
+    centroid: Vector,
+}
+
+#[derive(Copy, Clone)]
+struct Bucket {
+    count: usize,
+    bounds: BoundingBox,
+}
+
+impl Bvh {
+    pub fn empty() -> Bvh {
+	Bvh {
+	    nodes: Vec::new(),
+	    indices: Vec::new(),
+	}
+    }
+
+    // builds the hierarchy using the surface area heuristic,
+    // the n-th bounding box belongs to the primitive with index n
+    pub fn build(bounds: &[BoundingBox]) -> Bvh {
+	let mut primitives: Vec<BuildPrimitive> = bounds.iter().enumerate().map(|(index, bounds)| {
+	    BuildPrimitive {
+		index,
+		bounds: *bounds,
+		centroid: bounds.centroid(),
+	    }
+	}).collect();
+
+	let mut bvh = Bvh {
+	    nodes: Vec::with_capacity(2 * primitives.len()),
+	    indices: Vec::with_capacity(primitives.len()),
+	};
+
+	if !primitives.is_empty() {
+	    bvh.build_recursive(&mut primitives);
+	}
+
+	bvh
+    }
+
+    pub fn num_primitives(&self) -> usize {
+	self.indices.len()
+    }
+
+    // returns the index of the node that was created
+    fn build_recursive(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+	let node_index = self.nodes.len();
+
+	let bounds = primitives.iter().fold(BoundingBox::empty(), |b, p| b.union(p.bounds));
+	let centroid_bounds = primitives.iter().fold(BoundingBox::empty(), |b, p| b.include(p.centroid));
+
+	self.nodes.push(BvhNode {
+	    bounds,
+	    offset: 0,
+	    count: 0,
+	    axis: 0,
+	});
+
+	let split = if primitives.len() <= 1 {
+	    None
+	} else {
+	    Bvh::find_split(primitives, bounds, centroid_bounds)
+	};
+
+	match split {
+	    None => {
+		self.nodes[node_index].offset = self.indices.len();
+		self.nodes[node_index].count = primitives.len();
+		for primitive in primitives.iter() {
+		    self.indices.push(primitive.index);
+		}
+	    },
+	    Some((axis, split_position)) => {
+		let mut middle = partition(primitives, |p| p.centroid.component(axis) < split_position);
+
+		// can happen when many centroids coincide, fall back to an even split
+		if middle == 0 || middle == primitives.len() {
+		    primitives.sort_by(|a, b| a.centroid.component(axis).partial_cmp(&b.centroid.component(axis)).unwrap());
+		    middle = primitives.len() / 2;
+		}
+
+		let (first, second) = primitives.split_at_mut(middle);
+		self.build_recursive(first);
+		let second_index = self.build_recursive(second);
+
+		self.nodes[node_index].offset = second_index;
+		self.nodes[node_index].axis = axis;
+	    },
+	}
+
+	node_index
+    }
+
+    // finds the cheapest split according to the surface area heuristic,
+    // returns None when keeping all primitives in a single leaf is cheaper
+    fn find_split(primitives: &[BuildPrimitive], bounds: BoundingBox, centroid_bounds: BoundingBox) -> Option<(usize, f32)> {
+	let leaf_cost = INTERSECTION_COST * primitives.len() as f32;
+	let parent_area = bounds.surface_area();
+
+	let mut best_cost = f32::INFINITY;
+	let mut best_split = None;
+
+	for axis in 0 .. 3 {
+	    let axis_min = centroid_bounds.min.component(axis);
+	    let axis_max = centroid_bounds.max.component(axis);
+	    if axis_max <= axis_min {
+		continue;
+	    }
+
+	    let mut buckets = [Bucket { count: 0, bounds: BoundingBox::empty() }; NUM_BUCKETS];
+	    let bucket_of = |position: f32| -> usize {
+		let relative = (position - axis_min) / (axis_max - axis_min);
+		((relative * NUM_BUCKETS as f32) as usize).min(NUM_BUCKETS - 1)
+	    };
+
+	    for primitive in primitives {
+		let bucket = &mut buckets[bucket_of(primitive.centroid.component(axis))];
+		bucket.count += 1;
+		bucket.bounds = bucket.bounds.union(primitive.bounds);
+	    }
+
+	    // sweep from the right to know the cost of everything right of each split
+	    let mut right_area = [0f32; NUM_BUCKETS];
+	    let mut right_count = [0usize; NUM_BUCKETS];
+	    let mut accumulated = Bucket { count: 0, bounds: BoundingBox::empty() };
+	    for n in (1 .. NUM_BUCKETS).rev() {
+		accumulated.count += buckets[n].count;
+		accumulated.bounds = accumulated.bounds.union(buckets[n].bounds);
+		right_area[n] = accumulated.bounds.surface_area();
+		right_count[n] = accumulated.count;
+	    }
+
+	    // split n puts buckets 0 .. n on the left and n .. NUM_BUCKETS on the right
+	    let mut accumulated = Bucket { count: 0, bounds: BoundingBox::empty() };
+	    for n in 1 .. NUM_BUCKETS {
+		accumulated.count += buckets[n - 1].count;
+		accumulated.bounds = accumulated.bounds.union(buckets[n - 1].bounds);
+
+		if accumulated.count == 0 || right_count[n] == 0 {
+		    continue;
+		}
+
+		let cost = TRAVERSAL_COST + INTERSECTION_COST * (
+		    accumulated.bounds.surface_area() * accumulated.count as f32 +
+		    right_area[n] * right_count[n] as f32
+		) / parent_area;
+
+		if cost < best_cost {
+		    best_cost = cost;
+		    best_split = Some((axis, axis_min + (axis_max - axis_min) * n as f32 / NUM_BUCKETS as f32));
+		}
+	    }
+	}
+
+	if best_cost < leaf_cost || primitives.len() > MAX_PRIMITIVES_IN_LEAF {
+	    match best_split {
+		Some(split) => Some(split),
+		None => {
+		    // all centroids coincide, split along the longest axis of the bounds
+		    if primitives.len() > MAX_PRIMITIVES_IN_LEAF {
+			let extent = bounds.extent();
+			let axis = if extent.x >= extent.y && extent.x >= extent.z {
+			    0
+			} else if extent.y >= extent.z {
+			    1
+			} else {
+			    2
+			};
+			Some((axis, centroid_bounds.centroid().component(axis)))
+		    } else {
+			None
+		    }
+		},
+	    }
+	} else {
+	    None
+	}
+    }
+
+    // finds the closest hit along the ray
+    // intersect_primitive is called with the index of a primitive and returns the depth of the hit together with any other data about the hit
+    pub fn closest_hit<T, F>(&self, ray: Ray, mut intersect_primitive: F) -> Option<(f32, T)>
+    where F: FnMut(usize) -> Option<(f32, T)> {
+	if self.nodes.is_empty() {
+	    return None;
+	}
+
+	let inverse_direction = Vector {
+	    x: 1f32 / ray.direction.x,
+	    y: 1f32 / ray.direction.y,
+	    z: 1f32 / ray.direction.z,
+	};
+	let direction_negative = [ray.direction.x < 0f32, ray.direction.y < 0f32, ray.direction.z < 0f32];
+
+	let mut best_hit: Option<(f32, T)> = None;
+	let mut best_depth = ray.t_max;
+
+	let mut stack: Vec<usize> = Vec::with_capacity(64);
+	stack.push(0);
+
+	while let Some(node_index) = stack.pop() {
+	    let node = &self.nodes[node_index];
+
+	    if node.bounds.intersect(ray, inverse_direction, best_depth).is_none() {
+		continue;
+	    }
+
+	    if node.count > 0 {
+		for &primitive_index in &self.indices[node.offset .. node.offset + node.count] {
+		    if let Some((depth, hit)) = intersect_primitive(primitive_index) {
+			if depth < best_depth {
+			    best_depth = depth;
+			    best_hit = Some((depth, hit));
+			}
+		    }
+		}
+	    } else if direction_negative[node.axis] {
+		// visit the second child first, so push it last
+		stack.push(node_index + 1);
+		stack.push(node.offset);
+	    } else {
+		stack.push(node.offset);
+		stack.push(node_index + 1);
+	    }
+	}
+
+	best_hit
+    }
+
+    // whether the ray hits any primitive, stops at the first one found instead of looking for the closest
+    // hits_primitive is called with the index of a primitive
+    pub fn any_hit<F>(&self, ray: Ray, mut hits_primitive: F) -> bool
+    where F: FnMut(usize) -> bool {
+	if self.nodes.is_empty() {
+	    return false;
+	}
+
+	let inverse_direction = Vector {
+	    x: 1f32 / ray.direction.x,
+	    y: 1f32 / ray.direction.y,
+	    z: 1f32 / ray.direction.z,
+	};
+
+	let mut stack: Vec<usize> = Vec::with_capacity(64);
+	stack.push(0);
+
+	while let Some(node_index) = stack.pop() {
+	    let node = &self.nodes[node_index];
+
+	    if node.bounds.intersect(ray, inverse_direction, ray.t_max).is_none() {
+		continue;
+	    }
+
+	    if node.count > 0 {
+		if self.indices[node.offset .. node.offset + node.count].iter().any(|&primitive_index| hits_primitive(primitive_index)) {
+		    return true;
+		}
+	    } else {
+		stack.push(node.offset);
+		stack.push(node_index + 1);
+	    }
+	}
+
+	false
+    }
+}
+
+// moves all elements for which the predicate holds to the front, returns the number of such elements
+fn partition<T, P>(elements: &mut [T], predicate: P) -> usize
+where P: Fn(&T) -> bool {
+    let mut middle = 0;
+    for n in 0 .. elements.len() {
+	if predicate(&elements[n]) {
+	    elements.swap(n, middle);
+	    middle += 1;
+	}
+    }
+    middle
+}
@@ -11,9 +11,6 @@ pub struct Camera {
 
 impl Camera {
     pub fn shoot_ray(self, x: f32, y: f32) -> Ray {
-	Ray {
-	    origin: self.position,
-	    direction: (self.forward + self.right * x + self.up * y).normalised(),
-	}
+	Ray::new(self.position, (self.forward + self.right * x + self.up * y).normalised())
     }
 }
@@ -0,0 +1,135 @@
+use std::convert::TryFrom;
+
+use crate::scene::*;
+use crate::renderer::*;
+
+pub const USAGE: &str = "\
+usage: raytracer [options] [scene file]
+
+renders the scene file (scenes/cornell.scene by default) to a png image,
+options override the settings in the scene file
+
+options:
+  -o, --output <path>          where to save the image (test.png by default)
+  -r, --resolution <w>x<h>     image size in pixels, for example 1920x1080
+  -s, --samples <n>            samples per pixel
+  -b, --bounces <n>            bounces per path before they may be ended at random
+  -m, --sampling <method>      uniform, naive or mis
+  -t, --threads <n>            number of threads (one per core by default)
+  -h, --help                   print this message";
+
+// everything that can be set on the command line, None when not given
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub scene_path: String,
+    pub output_path: String,
+    pub resolution: Option<(usize, usize)>,
+    pub num_samples: Option<usize>,
+    pub min_bounces: Option<i32>,
+    pub sampling_method: Option<SamplingMethod>,
+    pub num_threads: Option<usize>,
+    pub help: bool,
+}
+
+impl Options {
+    // args excludes the name of the program
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+	let mut options = Options {
+	    scene_path: String::from("scenes/cornell.scene"),
+	    output_path: String::from("test.png"),
+	    resolution: None,
+	    num_samples: None,
+	    min_bounces: None,
+	    sampling_method: None,
+	    num_threads: None,
+	    help: false,
+	};
+
+	let mut scene_path = None;
+	let mut args = args.iter();
+
+	while let Some(arg) = args.next() {
+	    let mut value = || match args.next() {
+		Some(value) => Ok(value.as_str()),
+		None => Err(format!("{} needs a value", arg)),
+	    };
+
+	    match arg.as_str() {
+		"-h" | "--help" => options.help = true,
+		"-o" | "--output" => options.output_path = String::from(value()?),
+		"-r" | "--resolution" => options.resolution = Some(parse_resolution(value()?)?),
+		"-s" | "--samples" => options.num_samples = Some(parse_positive(arg, value()?)?),
+		"-b" | "--bounces" => options.min_bounces = Some(parse_bounces(arg, value()?)?),
+		"-m" | "--sampling" => {
+		    let name = value()?;
+		    options.sampling_method = match SamplingMethod::from_name(name) {
+			Some(method) => Some(method),
+			None => return Err(format!("unknown sampling method '{}', expected one of {}", name, SamplingMethod::NAMES.join(", "))),
+		    };
+		},
+		"-t" | "--threads" => options.num_threads = Some(parse_positive(arg, value()?)?),
+		_ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+		_ => {
+		    if scene_path.is_some() {
+			return Err(format!("only one scene file can be given, found {} as well", arg));
+		    }
+		    scene_path = Some(arg.clone());
+		},
+	    }
+	}
+
+	if let Some(scene_path) = scene_path {
+	    options.scene_path = scene_path;
+	}
+
+	Ok(options)
+    }
+
+    pub fn apply(&self, settings: &mut RenderSettings) {
+	if let Some((width, height)) = self.resolution {
+	    settings.width = width;
+	    settings.height = height;
+	}
+	if let Some(num_samples) = self.num_samples {
+	    settings.num_samples = num_samples;
+	}
+	if let Some(min_bounces) = self.min_bounces {
+	    settings.min_bounces = min_bounces;
+	}
+	if let Some(sampling_method) = self.sampling_method {
+	    settings.sampling_method = sampling_method;
+	}
+	if let Some(num_threads) = self.num_threads {
+	    settings.num_threads = num_threads;
+	}
+    }
+}
+
+fn parse_count(option: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("{} expects a number, found '{}'", option, value))
+}
+
+fn parse_bounces(option: &str, value: &str) -> Result<i32, String> {
+    i32::try_from(parse_count(option, value)?).map_err(|_| format!("{} can be at most {}", option, i32::MAX))
+}
+
+fn parse_positive(option: &str, value: &str) -> Result<usize, String> {
+    match parse_count(option, value)? {
+	0 => Err(format!("{} must be at least 1", option)),
+	n => Ok(n),
+    }
+}
+
+fn parse_resolution(value: &str) -> Result<(usize, usize), String> {
+    let error = || format!("expected a resolution like 1920x1080, found '{}'", value);
+
+    let mut parts = value.split('x');
+    let width = parts.next().ok_or_else(error)?.parse::<usize>().map_err(|_| error())?;
+    let height = parts.next().ok_or_else(error)?.parse::<usize>().map_err(|_| error())?;
+
+    if parts.next().is_some() || width == 0 || height == 0 {
+	return Err(error());
+    }
+
+    Ok((width, height))
+}
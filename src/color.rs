@@ -44,16 +44,40 @@ impl Mul<Color> for Color {
 }
 
 impl Color {
+    // average over the channels
+    pub fn brightness(self) -> f32 {
+	(self.r + self.g + self.b) / 3f32
+    }
+
+    pub fn max_channel(self) -> f32 {
+	self.r.max(self.g).max(self.b)
+    }
+
     pub fn quantise(self) -> image::Rgb<u8> {
-	let r: u8 = (self.r * 255f32).min(255f32).max(0f32) as u8;
-	let g: u8 = (self.g * 255f32).min(255f32).max(0f32) as u8;
-	let b: u8 = (self.b * 255f32).min(255f32).max(0f32) as u8;
+	let r: u8 = (self.r * 255f32).clamp(0f32, 255f32) as u8;
+	let g: u8 = (self.g * 255f32).clamp(0f32, 255f32) as u8;
+	let b: u8 = (self.b * 255f32).clamp(0f32, 255f32) as u8;
 	image::Rgb([r, g, b])
     }
 }
 
+// converts a channel from the srgb transfer curve, as used by most image files, to linear light
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045f32 {
+	value / 12.92f32
+    } else {
+	((value + 0.055f32) / 1.055f32).powf(2.4f32)
+    }
+}
+
 pub const BLACK: Color = Color {
     r: 0f32,
     g: 0f32,
     b: 0f32,
 };
+
+pub const WHITE: Color = Color {
+    r: 1f32,
+    g: 1f32,
+    b: 1f32,
+};
@@ -0,0 +1,126 @@
+use crate::vector::*;
+use crate::color::*;
+use crate::microfacet::*;
+use crate::surface_element::*;
+use crate::bsdf::*;
+
+// metal, reflecting according to the fresnel equations for its complex index of refraction eta + i k
+#[derive(Copy, Clone, Debug)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub roughness: f32, // 0 for a perfect mirror, up to 1 for a very dull surface
+}
+
+impl Conductor {
+    // names as used in scene files
+    pub const PRESETS: [&'static str; 4] = ["gold", "copper", "aluminium", "silver"];
+
+    // measured indices of refraction at the red, green and blue wavelengths (650, 550 and 450 nm)
+    pub fn preset(name: &str) -> Option<Conductor> {
+	let (eta, k) = match name {
+	    "gold"      => ((0.143f32, 0.374f32, 1.442f32), (3.983f32, 2.385f32, 1.603f32)),
+	    "copper"    => ((0.200f32, 0.924f32, 1.102f32), (3.912f32, 2.452f32, 2.142f32)),
+	    "aluminium" => ((1.657f32, 0.880f32, 0.521f32), (9.224f32, 6.270f32, 4.837f32)),
+	    "silver"    => ((0.155f32, 0.117f32, 0.138f32), (4.828f32, 3.122f32, 2.147f32)),
+	    _ => return None,
+	};
+
+	Some(Conductor {
+	    eta: Color { r: eta.0, g: eta.1, b: eta.2 },
+	    k: Color { r: k.0, g: k.1, b: k.2 },
+	    roughness: 0f32,
+	})
+    }
+
+    // width of the ggx distribution, squaring the roughness makes it change more evenly to the eye
+    fn alpha(self) -> f32 {
+	self.roughness * self.roughness
+    }
+
+    pub fn fresnel(self, cos_theta: f32) -> Color {
+	Color {
+	    r: fresnel_conductor(cos_theta, self.eta.r, self.k.r),
+	    g: fresnel_conductor(cos_theta, self.eta.g, self.k.g),
+	    b: fresnel_conductor(cos_theta, self.eta.b, self.k.b),
+	}
+    }
+}
+
+impl Bsdf for Conductor {
+    fn eval(&self, _surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> Color {
+	let alpha = self.alpha();
+	if alpha < MIN_ALPHA || wo.z <= 0f32 || wi.z <= 0f32 {
+	    return BLACK;
+	}
+
+	let microfacet_normal = (wo + wi).normalised();
+	self.fresnel(dot(wo, microfacet_normal)) * (
+	    ggx_distribution(microfacet_normal, alpha) * smith_g2(wo, wi, alpha) / (4f32 * wo.z * wi.z)
+	)
+    }
+
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, u: [f32; 3]) -> Option<BsdfSample> {
+	if wo.z <= 0f32 {
+	    return None;
+	}
+
+	let alpha = self.alpha();
+	if alpha < MIN_ALPHA {
+	    return Some(BsdfSample {
+		wi: Vector { x: -wo.x, y: -wo.y, z: wo.z },
+		weight: self.fresnel(wo.z),
+		pdf: 1f32,
+	    });
+	}
+
+	// with visible normals sampled the brdf and most of the pdf cancel out
+	let microfacet_normal = sample_visible_normal(wo, alpha, u[0], u[1]);
+	let wi = reflect(wo, microfacet_normal);
+	if wi.z <= 0f32 {
+	    return None;
+	}
+
+	Some(BsdfSample {
+	    wi,
+	    weight: self.fresnel(dot(wo, microfacet_normal)) * (smith_g2(wo, wi, alpha) / smith_g1(wo, alpha)),
+	    pdf: self.pdf(surface_element, wo, wi),
+	})
+    }
+
+    fn pdf(&self, _surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> f32 {
+	let alpha = self.alpha();
+	if alpha < MIN_ALPHA || wo.z <= 0f32 || wi.z <= 0f32 {
+	    return 0f32;
+	}
+
+	// the density of the normal, changed into that of the reflected direction
+	let microfacet_normal = (wo + wi).normalised();
+	visible_normal_pdf(wo, microfacet_normal, alpha) / (4f32 * dot(wo, microfacet_normal))
+    }
+
+    fn is_specular(&self) -> bool {
+	self.alpha() < MIN_ALPHA
+    }
+}
+
+// fraction of the light reflected by a conductor for one wavelength, averaged over both polarisations
+fn fresnel_conductor(cos_theta: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_theta.clamp(0f32, 1f32).powi(2);
+    let sin2 = 1f32 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4f32 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5f32 * (a2_plus_b2 + t0)).max(0f32).sqrt();
+    let t2 = 2f32 * cos_theta.clamp(0f32, 1f32) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5f32 * (rp + rs)
+}
@@ -0,0 +1,130 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::disc::*;
+use crate::shape::*;
+
+use std::f32::consts::PI;
+
+// closed at the base by a disc, seen from the outside
+#[derive(Copy, Clone, Debug)]
+pub struct Cone {
+    pub placement: Placement, // origin at the center of the base, z axis towards the apex
+    pub radius: f32, // of the base
+    pub height: f32,
+    pub material: usize, // index into Scene::materials
+}
+
+impl Cone {
+    pub fn new(base: Vector, apex: Vector, radius: f32, material: usize) -> Cone {
+	Cone {
+	    placement: Placement::new(base, apex - base),
+	    radius,
+	    height: (apex - base).norm(),
+	    material,
+	}
+    }
+
+    fn side_area(self) -> f32 {
+	PI * self.radius * (self.radius * self.radius + self.height * self.height).sqrt()
+    }
+
+    fn base_area(self) -> f32 {
+	PI * self.radius * self.radius
+    }
+
+    // the outward normal of the side at a point on it, straight up at the apex, where it is undefined
+    fn side_normal(self, point: Vector) -> Vector {
+	let k = self.radius / self.height;
+	let normal = Vector { x: point.x, y: point.y, z: k * k * (self.height - point.z) };
+	if normal.norm2() > 0f32 { normal.normalised() } else { Vector { x: 0f32, y: 0f32, z: 1f32 } }
+    }
+
+    // the hit with the side, x^2 + y^2 = (k (h - z))^2 between the base and the apex,
+    // where u goes around the axis and v from the base (0) to the apex (1)
+    fn intersect_side(self, ray: Ray) -> Option<LocalHit> {
+	let (o, d) = (ray.origin, ray.direction);
+	let (ox, oy, oz) = (o.x as f64, o.y as f64, o.z as f64);
+	let (dx, dy, dz) = (d.x as f64, d.y as f64, d.z as f64);
+	let k2 = (self.radius as f64 / self.height as f64).powi(2);
+	let h = self.height as f64 - oz; // height of the apex above the origin
+
+	let a = dx * dx + dy * dy - k2 * dz * dz;
+	let b = 2f64 * (ox * dx + oy * dy + k2 * h * dz);
+	let c = ox * ox + oy * oy - k2 * h * h;
+	let (t0, t1) = solve_quadratic(a, b, c)?;
+
+	[t0, t1].iter()
+	    .map(|&t| t as f32)
+	    .filter(|&t| t >= ray.t_min && t <= ray.t_max)
+	    .map(|depth| (depth, ray.origin + ray.direction * depth))
+	    .find(|(_, hit)| hit.z >= 0f32 && hit.z <= self.height)
+	    .map(|(depth, hit)| {
+		// moved onto the side, away from the axis
+		let distance = (hit.x * hit.x + hit.y * hit.y).sqrt();
+		let radius = self.radius * (1f32 - hit.z / self.height);
+		let point = if distance > 0f32 {
+		    Vector { x: hit.x * radius / distance, y: hit.y * radius / distance, z: hit.z }
+		} else {
+		    hit
+		};
+
+		// the direction of increasing u is undefined at the apex
+		let tangent = if distance > 0f32 { Vector { x: -point.y, y: point.x, z: 0f32 } } else { Vector { x: 0f32, y: 1f32, z: 0f32 } };
+
+		LocalHit {
+		    depth,
+		    point,
+		    normal: self.side_normal(point),
+		    tangent,
+		    texture_coordinates: (turn_fraction(point.x, point.y), point.z / self.height),
+		}
+	    })
+    }
+}
+
+impl Shape for Cone {
+    // the disc at the base has the texture coordinates of Disc
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	let local = closest_local_hit(local_ray, &[
+	    self.intersect_side(local_ray),
+	    intersect_disc(local_ray, 0f32, self.radius, true),
+	])?;
+
+	Some((local.depth + shift, self.placement.surface_element(local, local.point.abs() * rounding_error(5), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	self.placement.bounds(
+	    Vector { x: -self.radius, y: -self.radius, z: 0f32 },
+	    Vector { x: self.radius, y: self.radius, z: self.height },
+	)
+    }
+
+    fn area(&self) -> f32 {
+	self.side_area() + self.base_area()
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    // the side or the base is picked by area with p1, which is then reused for picking the point on it,
+    // on the side the density of the distance s from the apex grows as s
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let side_fraction = self.side_area() / self.area();
+	let (point, normal) = if p1 < side_fraction {
+	    let s = (p1 / side_fraction).sqrt();
+	    let phi = 2f32 * PI * p2;
+	    let point = Vector { x: s * self.radius * phi.cos(), y: s * self.radius * phi.sin(), z: self.height * (1f32 - s) };
+	    (point, self.side_normal(point))
+	} else {
+	    let (x, y) = sample_unit_disc((p1 - side_fraction) / (1f32 - side_fraction), p2);
+	    (Vector { x: x * self.radius, y: y * self.radius, z: 0f32 }, Vector { x: 0f32, y: 0f32, z: -1f32 })
+	};
+
+	self.placement.sample_to_world(point, normal, 1f32 / self.area())
+    }
+}
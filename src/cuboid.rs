@@ -0,0 +1,160 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::frame::*;
+use crate::shape::*;
+
+// box with its sides along the axes of its placement, which are the axes of the scene for an axis-aligned box,
+// seen from the outside
+#[derive(Copy, Clone, Debug)]
+pub struct Cuboid {
+    pub placement: Placement, // origin at the center
+    pub half_size: Vector, // distance from the center to the sides along each axis
+    pub material: usize, // index into Scene::materials
+}
+
+impl Cuboid {
+    pub fn new(center: Vector, axes: Frame, size: Vector, material: usize) -> Cuboid {
+	Cuboid {
+	    placement: Placement {
+		origin: center,
+		frame: axes,
+	    },
+	    half_size: size * 0.5f32,
+	    material,
+	}
+    }
+
+    // areas of the pairs of sides facing along the x, y and z axes
+    fn side_areas(self) -> [f32; 3] {
+	let Vector { x, y, z } = self.half_size;
+	[8f32 * y * z, 8f32 * z * x, 8f32 * x * y]
+    }
+
+    // the point on the side facing along the given axis, to the negative side when flipped, at the given coordinates
+    // along the other two axes, with the normal there and the tangent along the first of those axes,
+    // u and v go from 0 to 1 along them
+    fn side(self, axis: usize, flipped: bool, a: f32, b: f32) -> (Vector, Vector, Vector, (f32, f32)) {
+	let half_size = component_array(self.half_size);
+	let (first, second) = ((axis + 1) % 3, (axis + 2) % 3);
+
+	let mut point = [0f32; 3];
+	point[axis] = if flipped { -half_size[axis] } else { half_size[axis] };
+	point[first] = a;
+	point[second] = b;
+
+	let mut normal = [0f32; 3];
+	normal[axis] = if flipped { -1f32 } else { 1f32 };
+	let mut tangent = [0f32; 3];
+	tangent[first] = 1f32;
+
+	let texture_coordinates = (
+	    (0.5f32 + 0.5f32 * a / half_size[first]).clamp(0f32, 1f32),
+	    (0.5f32 + 0.5f32 * b / half_size[second]).clamp(0f32, 1f32),
+	);
+	(from_array(point), from_array(normal), from_array(tangent), texture_coordinates)
+    }
+}
+
+impl Shape for Cuboid {
+    // slab test in the coordinates of the box, the side the ray enters through is hit unless it starts inside,
+    // then the one it leaves through
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	let origin = component_array(local_ray.origin);
+	let direction = component_array(local_ray.direction);
+	let half_size = component_array(self.half_size);
+
+	let (mut t_enter, mut enter_axis) = (-f32::INFINITY, 0);
+	let (mut t_exit, mut exit_axis) = (f32::INFINITY, 0);
+	for axis in 0 .. 3 {
+	    if direction[axis] == 0f32 {
+		// parallel to the sides, the ray stays between them or misses
+		if origin[axis].abs() > half_size[axis] {
+		    return None;
+		}
+		continue;
+	    }
+
+	    let t1 = (-half_size[axis] - origin[axis]) / direction[axis];
+	    let t2 = (half_size[axis] - origin[axis]) / direction[axis];
+	    let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+	    if near > t_enter {
+		t_enter = near;
+		enter_axis = axis;
+	    }
+	    if far < t_exit {
+		t_exit = far;
+		exit_axis = axis;
+	    }
+	}
+
+	if t_enter > t_exit {
+	    return None;
+	}
+
+	let entering = t_enter >= local_ray.t_min;
+	let (depth, axis) = if entering { (t_enter, enter_axis) } else { (t_exit, exit_axis) };
+	if depth < local_ray.t_min || depth > local_ray.t_max {
+	    return None;
+	}
+
+	// the point is put exactly on the side it hit, only the coordinates along it carry rounding errors
+	let hit = component_array(local_ray.origin + local_ray.direction * depth);
+	let (first, second) = ((axis + 1) % 3, (axis + 2) % 3);
+	// a ray going up an axis enters through the side facing down it
+	let flipped = (direction[axis] > 0f32) == entering;
+	let a = hit[first].clamp(-half_size[first], half_size[first]);
+	let b = hit[second].clamp(-half_size[second], half_size[second]);
+	let (point, normal, tangent, texture_coordinates) = self.side(axis, flipped, a, b);
+
+	let local = LocalHit {
+	    depth,
+	    point,
+	    normal,
+	    tangent,
+	    texture_coordinates,
+	};
+	Some((depth + shift, self.placement.surface_element(local, point.abs() * rounding_error(3), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	self.placement.bounds(-self.half_size, self.half_size)
+    }
+
+    fn area(&self) -> f32 {
+	self.side_areas().iter().sum()
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    // a pair of sides is picked by area with p1, which is then reused for picking one of the two and the point on it
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let areas = self.side_areas();
+	let mut p = p1 * self.area();
+	let mut axis = 0;
+	while axis < 2 && p >= areas[axis] {
+	    p -= areas[axis];
+	    axis += 1;
+	}
+	let p = (p / areas[axis]).clamp(0f32, 1f32);
+	let (flipped, p) = if p < 0.5f32 { (true, 2f32 * p) } else { (false, 2f32 * p - 1f32) };
+
+	let half_size = component_array(self.half_size);
+	let (first, second) = ((axis + 1) % 3, (axis + 2) % 3);
+	let (point, normal, _, _) = self.side(axis, flipped, (2f32 * p - 1f32) * half_size[first], (2f32 * p2 - 1f32) * half_size[second]);
+
+	self.placement.sample_to_world(point, normal, 1f32 / self.area())
+    }
+}
+
+fn component_array(v: Vector) -> [f32; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn from_array([x, y, z]: [f32; 3]) -> Vector {
+    Vector { x, y, z }
+}
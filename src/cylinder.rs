@@ -0,0 +1,116 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::disc::*;
+use crate::shape::*;
+
+use std::f32::consts::PI;
+
+// closed at both ends by discs, seen from the outside
+#[derive(Copy, Clone, Debug)]
+pub struct Cylinder {
+    pub placement: Placement, // origin at the center of the base, z axis along the axis of the cylinder
+    pub radius: f32,
+    pub height: f32,
+    pub material: usize, // index into Scene::materials
+}
+
+impl Cylinder {
+    pub fn new(base: Vector, top: Vector, radius: f32, material: usize) -> Cylinder {
+	Cylinder {
+	    placement: Placement::new(base, top - base),
+	    radius,
+	    height: (top - base).norm(),
+	    material,
+	}
+    }
+
+    fn side_area(self) -> f32 {
+	2f32 * PI * self.radius * self.height
+    }
+
+    fn cap_area(self) -> f32 {
+	PI * self.radius * self.radius
+    }
+
+    // the hit with the side, where u goes around the axis and v from the base (0) to the top (1)
+    fn intersect_side(self, ray: Ray) -> Option<LocalHit> {
+	let (o, d) = (ray.origin, ray.direction);
+	let a = d.x as f64 * d.x as f64 + d.y as f64 * d.y as f64;
+	let b = 2f64 * (o.x as f64 * d.x as f64 + o.y as f64 * d.y as f64);
+	let c = o.x as f64 * o.x as f64 + o.y as f64 * o.y as f64 - self.radius as f64 * self.radius as f64;
+	if a == 0f64 {
+	    return None; // along the axis
+	}
+	let (t0, t1) = solve_quadratic(a, b, c)?;
+
+	[t0, t1].iter()
+	    .map(|&t| t as f32)
+	    .filter(|&t| t >= ray.t_min && t <= ray.t_max)
+	    .map(|depth| (depth, ray.origin + ray.direction * depth))
+	    .find(|(_, hit)| hit.z >= 0f32 && hit.z <= self.height)
+	    .map(|(depth, hit)| {
+		// moved onto the side, away from the axis
+		let scale = self.radius / (hit.x * hit.x + hit.y * hit.y).sqrt();
+		let point = Vector { x: hit.x * scale, y: hit.y * scale, z: hit.z };
+		LocalHit {
+		    depth,
+		    point,
+		    normal: Vector { x: point.x / self.radius, y: point.y / self.radius, z: 0f32 },
+		    tangent: Vector { x: -point.y, y: point.x, z: 0f32 },
+		    texture_coordinates: (turn_fraction(point.x, point.y), point.z / self.height),
+		}
+	    })
+    }
+}
+
+impl Shape for Cylinder {
+    // the discs at the ends have the texture coordinates of Disc
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	let local = closest_local_hit(local_ray, &[
+	    self.intersect_side(local_ray),
+	    intersect_disc(local_ray, 0f32, self.radius, true),
+	    intersect_disc(local_ray, self.height, self.radius, false),
+	])?;
+
+	Some((local.depth + shift, self.placement.surface_element(local, local.point.abs() * rounding_error(5), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	self.placement.bounds(
+	    Vector { x: -self.radius, y: -self.radius, z: 0f32 },
+	    Vector { x: self.radius, y: self.radius, z: self.height },
+	)
+    }
+
+    fn area(&self) -> f32 {
+	self.side_area() + 2f32 * self.cap_area()
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    // the side or one of the ends is picked by area with p1, which is then reused for picking the point on it
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let side_fraction = self.side_area() / self.area();
+	let (point, normal) = if p1 < side_fraction {
+	    let z = self.height * p1 / side_fraction;
+	    let phi = 2f32 * PI * p2;
+	    let normal = Vector { x: phi.cos(), y: phi.sin(), z: 0f32 };
+	    (Vector { x: normal.x * self.radius, y: normal.y * self.radius, z }, normal)
+	} else {
+	    let p = (p1 - side_fraction) / (1f32 - side_fraction);
+	    let top = p >= 0.5f32;
+	    let (x, y) = sample_unit_disc(if top { 2f32 * p - 1f32 } else { 2f32 * p }, p2);
+	    (
+		Vector { x: x * self.radius, y: y * self.radius, z: if top { self.height } else { 0f32 } },
+		Vector { x: 0f32, y: 0f32, z: if top { 1f32 } else { -1f32 } },
+	    )
+	};
+
+	self.placement.sample_to_world(point, normal, 1f32 / self.area())
+    }
+}
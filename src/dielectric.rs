@@ -0,0 +1,150 @@
+use crate::vector::*;
+use crate::color::*;
+use crate::microfacet::*;
+use crate::surface_element::*;
+use crate::bsdf::*;
+
+// transparent material such as glass or water, which reflects part of the light and refracts the rest
+#[derive(Copy, Clone, Debug)]
+pub struct Dielectric {
+    pub ior: f32, // index of refraction of the inside, the outside is taken to be air
+    pub roughness: f32, // 0 for polished, up to 1 for frosted
+    pub absorption: Color, // per unit of distance travelled inside, following the beer-lambert law
+}
+
+impl Dielectric {
+    fn alpha(self) -> f32 {
+	self.roughness * self.roughness
+    }
+
+    // mirrors both directions to the side of the normal when wo is inside, so that side can be treated as the outside,
+    // along with eta, the index of refraction on the other side divided by that on the side of wo
+    fn oriented(self, wo: Vector, wi: Vector) -> (Vector, Vector, f32) {
+	if wo.z < 0f32 {
+	    (flip(wo), flip(wi), 1f32 / self.ior)
+	} else {
+	    (wo, wi, self.ior)
+	}
+    }
+}
+
+impl Bsdf for Dielectric {
+    fn eval(&self, _surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> Color {
+	let alpha = self.alpha();
+	if alpha < MIN_ALPHA || wo.z == 0f32 || wi.z == 0f32 {
+	    return BLACK;
+	}
+	let (wo, wi, eta) = self.oriented(wo, wi);
+
+	let value = if wi.z > 0f32 {
+	    let microfacet_normal = (wo + wi).normalised();
+	    fresnel_dielectric(dot(wo, microfacet_normal), eta)
+		* ggx_distribution(microfacet_normal, alpha) * smith_g2(wo, wi, alpha) / (4f32 * wo.z * wi.z)
+	} else {
+	    let microfacet_normal = match refraction_normal(wo, wi, eta) {
+		Some(normal) => normal,
+		None => return BLACK,
+	    };
+	    let cos_o = dot(wo, microfacet_normal);
+	    let cos_i = dot(wi, microfacet_normal);
+	    let denominator = cos_o + eta * cos_i;
+
+	    // includes the compression of radiance into a smaller solid angle when entering a denser medium
+	    (1f32 - fresnel_dielectric(cos_o, eta))
+		* ggx_distribution(microfacet_normal, alpha) * smith_g2(wo, wi, alpha)
+		* (cos_o * cos_i).abs() / (wo.z * wi.z.abs() * denominator * denominator)
+	};
+
+	Color { r: value, g: value, b: value }
+    }
+
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, u: [f32; 3]) -> Option<BsdfSample> {
+	if wo.z == 0f32 {
+	    return None;
+	}
+	let inside = wo.z < 0f32;
+	let (wo_oriented, _, eta) = self.oriented(wo, wo);
+
+	let alpha = self.alpha();
+	let smooth = alpha < MIN_ALPHA;
+	let microfacet_normal = if smooth {
+	    Vector { x: 0f32, y: 0f32, z: 1f32 }
+	} else {
+	    sample_visible_normal(wo_oriented, alpha, u[0], u[1])
+	};
+
+	// reflection or refraction is chosen in proportion to the fresnel term, which then cancels out
+	let fresnel = fresnel_dielectric(dot(wo_oriented, microfacet_normal), eta);
+
+	let (wi, weight, probability) = if u[2] < fresnel {
+	    let wi = reflect(wo_oriented, microfacet_normal);
+	    if wi.z <= 0f32 {
+		return None;
+	    }
+	    (wi, 1f32, fresnel)
+	} else {
+	    let wi = refract(wo_oriented, microfacet_normal, eta)?;
+	    if wi.z >= 0f32 {
+		return None;
+	    }
+	    // radiance is compressed into a smaller solid angle when entering a denser medium
+	    (wi, 1f32 / (eta * eta), 1f32 - fresnel)
+	};
+
+	let masking = if smooth { 1f32 } else { smith_g2(wo_oriented, wi, alpha) / smith_g1(wo_oriented, alpha) };
+	let weight = weight * masking;
+	let wi = if inside { flip(wi) } else { wi };
+
+	Some(BsdfSample {
+	    wi,
+	    weight: Color { r: weight, g: weight, b: weight },
+	    pdf: if smooth { probability } else { self.pdf(surface_element, wo, wi) },
+	})
+    }
+
+    fn pdf(&self, _surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> f32 {
+	let alpha = self.alpha();
+	if alpha < MIN_ALPHA || wo.z == 0f32 || wi.z == 0f32 {
+	    return 0f32;
+	}
+	let (wo, wi, eta) = self.oriented(wo, wi);
+
+	// the density of the normal, changed into that of the reflected or refracted direction
+	if wi.z > 0f32 {
+	    let microfacet_normal = (wo + wi).normalised();
+	    let cos_o = dot(wo, microfacet_normal);
+	    fresnel_dielectric(cos_o, eta) * visible_normal_pdf(wo, microfacet_normal, alpha) / (4f32 * cos_o)
+	} else {
+	    let microfacet_normal = match refraction_normal(wo, wi, eta) {
+		Some(normal) => normal,
+		None => return 0f32,
+	    };
+	    let cos_o = dot(wo, microfacet_normal);
+	    let cos_i = dot(wi, microfacet_normal);
+	    let denominator = cos_o + eta * cos_i;
+
+	    (1f32 - fresnel_dielectric(cos_o, eta)) * visible_normal_pdf(wo, microfacet_normal, alpha)
+		* eta * eta * cos_i.abs() / (denominator * denominator)
+	}
+    }
+
+    fn is_specular(&self) -> bool {
+	self.alpha() < MIN_ALPHA
+    }
+
+    fn is_two_sided(&self) -> bool {
+	true
+    }
+
+    fn transmittance(&self, distance: f32) -> Color {
+	Color {
+	    r: (-self.absorption.r * distance).exp(),
+	    g: (-self.absorption.g * distance).exp(),
+	    b: (-self.absorption.b * distance).exp(),
+	}
+    }
+}
+
+fn flip(v: Vector) -> Vector {
+    Vector { x: v.x, y: v.y, z: -v.z }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::f32::consts::PI;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::texture::*;
+use crate::surface_element::*;
+use crate::material::*;
+use crate::bsdf::*;
+
+// lambertian reflection, equally bright from all directions
+#[derive(Debug)]
+pub struct Diffuse {
+    pub texture: Arc<dyn Texture>,
+}
+
+impl Bsdf for Diffuse {
+    fn eval(&self, surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> Color {
+	if wo.z <= 0f32 || wi.z <= 0f32 {
+	    return BLACK;
+	}
+	surface_color(self.texture.as_ref(), surface_element) * (1f32 / PI)
+    }
+
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, u: [f32; 3]) -> Option<BsdfSample> {
+	if wo.z <= 0f32 {
+	    return None;
+	}
+
+	// cosine weighed, so only the color remains of the brdf
+	let wi = cosine_weighed_direction(u[0], u[1]);
+	if wi.z <= 0f32 {
+	    return None;
+	}
+
+	Some(BsdfSample {
+	    wi,
+	    weight: surface_color(self.texture.as_ref(), surface_element),
+	    pdf: wi.z / PI,
+	})
+    }
+
+    fn pdf(&self, _surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> f32 {
+	if wo.z <= 0f32 || wi.z <= 0f32 {
+	    return 0f32;
+	}
+	wi.z / PI
+    }
+}
@@ -0,0 +1,80 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::shape::*;
+
+use std::f32::consts::PI;
+
+// flat and round, its front on the side the normal points to
+#[derive(Copy, Clone, Debug)]
+pub struct Disc {
+    pub placement: Placement, // origin at the center, z axis along the normal
+    pub radius: f32,
+    pub material: usize, // index into Scene::materials
+}
+
+impl Disc {
+    pub fn new(center: Vector, normal: Vector, radius: f32, material: usize) -> Disc {
+	Disc {
+	    placement: Placement::new(center, normal),
+	    radius,
+	    material,
+	}
+    }
+}
+
+impl Shape for Disc {
+    // u goes around the center counterclockwise, v from the center (0) to the rim (1)
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	let local = intersect_disc(local_ray, 0f32, self.radius, false)?;
+
+	Some((local.depth + shift, self.placement.surface_element(local, local.point.abs() * rounding_error(3), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	let extent = Vector { x: self.radius, y: self.radius, z: 0f32 };
+	self.placement.bounds(-extent, extent)
+    }
+
+    fn area(&self) -> f32 {
+	PI * self.radius * self.radius
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let (x, y) = sample_unit_disc(p1, p2);
+	let point = Vector { x: x * self.radius, y: y * self.radius, z: 0f32 };
+	self.placement.sample_to_world(point, Vector { x: 0f32, y: 0f32, z: 1f32 }, 1f32 / self.area())
+    }
+}
+
+// the hit with a disc around the z axis at the given height in the coordinates of a shape, facing up,
+// or down when flipped as the bottom of a closed shape, no matter the range of the ray
+pub fn intersect_disc(ray: Ray, z: f32, radius: f32, flipped: bool) -> Option<LocalHit> {
+    if ray.direction.z == 0f32 {
+	return None;
+    }
+
+    let depth = (z - ray.origin.z) / ray.direction.z;
+    let hit = ray.origin + ray.direction * depth;
+    let distance2 = hit.x * hit.x + hit.y * hit.y;
+    if distance2 > radius * radius || depth < ray.t_min || depth > ray.t_max {
+	return None;
+    }
+
+    // the direction of increasing u is undefined at the center
+    let tangent = if distance2 > 0f32 { Vector { x: -hit.y, y: hit.x, z: 0f32 } } else { Vector { x: 0f32, y: 1f32, z: 0f32 } };
+
+    Some(LocalHit {
+	depth,
+	point: Vector { x: hit.x, y: hit.y, z },
+	normal: Vector { x: 0f32, y: 0f32, z: if flipped { -1f32 } else { 1f32 } },
+	tangent,
+	texture_coordinates: (turn_fraction(hit.x, hit.y), distance2.sqrt() / radius),
+    })
+}
@@ -0,0 +1,61 @@
+// discrete distribution, for picking one out of several items with a probability proportional to its weight
+#[derive(Clone, Debug)]
+pub struct Distribution {
+    cdf: Vec<f32>, // cdf[n] is the sum of the weights of the items before and including n
+    total: f32,
+}
+
+impl Distribution {
+    pub fn empty() -> Distribution {
+	Distribution {
+	    cdf: Vec::new(),
+	    total: 0f32,
+	}
+    }
+
+    pub fn new(weights: &[f32]) -> Distribution {
+	let mut cdf = Vec::with_capacity(weights.len());
+	let mut total = 0f32;
+
+	for weight in weights {
+	    assert!(*weight >= 0f32);
+	    total += weight;
+	    cdf.push(total);
+	}
+
+	Distribution {
+	    cdf,
+	    total,
+	}
+    }
+
+    // false if there are no items, or if all of them have weight zero
+    pub fn can_sample(&self) -> bool {
+	self.total > 0f32
+    }
+
+    // sum of all weights
+    pub fn total(&self) -> f32 {
+	self.total
+    }
+
+    // maps p, uniform in [0, 1), to an index
+    pub fn sample(&self, p: f32) -> usize {
+	assert!(self.can_sample());
+
+	let target = p * self.total;
+	let index = self.cdf.partition_point(|&accumulated| accumulated <= target);
+
+	// guards against p * total being rounded up to total
+	index.min(self.cdf.len() - 1)
+    }
+
+    pub fn probability(&self, index: usize) -> f32 {
+	if self.total <= 0f32 {
+	    return 0f32;
+	}
+
+	let previous = if index == 0 { 0f32 } else { self.cdf[index - 1] };
+	(self.cdf[index] - previous) / self.total
+    }
+}
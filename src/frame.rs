@@ -0,0 +1,48 @@
+use crate::vector::*;
+
+// orthonormal basis around a normal, which becomes the z axis of the local frame
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    pub s: Vector,
+    pub t: Vector,
+    pub n: Vector,
+}
+
+impl Frame {
+    pub fn new(normal: Vector) -> Frame {
+	let (s, t) = normal.make_orthogonal_frame();
+	Frame {
+	    s,
+	    t,
+	    n: normal.normalised(),
+	}
+    }
+
+    // with s along the part of the tangent that is perpendicular to the normal, where there is one
+    pub fn with_tangent(normal: Vector, tangent: Vector) -> Frame {
+	let n = normal.normalised();
+	let perpendicular = tangent - n * dot(tangent, n);
+	if perpendicular.norm2() < 0.000001f32 {
+	    return Frame::new(normal);
+	}
+	let s = perpendicular.normalised();
+
+	Frame {
+	    s,
+	    t: cross(n, s),
+	    n,
+	}
+    }
+
+    pub fn to_local(self, v: Vector) -> Vector {
+	Vector {
+	    x: dot(v, self.s),
+	    y: dot(v, self.t),
+	    z: dot(v, self.n),
+	}
+    }
+
+    pub fn to_world(self, v: Vector) -> Vector {
+	self.s * v.x + self.t * v.y + self.n * v.z
+    }
+}
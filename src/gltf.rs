@@ -0,0 +1,148 @@
+// reads materials from gltf files in the json form (.gltf), the geometry in them is not loaded,
+// besides the metallic-roughness model the clearcoat, sheen, transmission, ior, specular, anisotropy and
+// emissive strength extensions are understood, of the textures only the base color is used
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::*;
+use crate::texture::*;
+use crate::image_texture::*;
+use crate::principled::*;
+use crate::json::*;
+
+// the material with the given name, along with the light it emits
+pub fn load_gltf_material(path: &str, name: &str) -> Result<(Principled, Color), String> {
+    println!("loading: {}", path);
+
+    let text = fs::read_to_string(path).map_err(|error| format!("couldn't read the file {}: {}", path, error))?;
+    let document = Json::parse(&text).map_err(|message| format!("{}: {}", path, message))?;
+
+    let materials = document.get("materials").and_then(Json::as_array).unwrap_or(&[]);
+    let material = match materials.iter().find(|material| material.get("name").and_then(Json::as_str) == Some(name)) {
+	Some(material) => material,
+	None => return Err(format!("{} has no material '{}'", path, name)),
+    };
+
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    parse_material(&document, material, directory).map_err(|message| format!("{} material '{}': {}", path, name, message))
+}
+
+fn parse_material(document: &Json, material: &Json, directory: &Path) -> Result<(Principled, Color), String> {
+    let pbr = material.get("pbrMetallicRoughness");
+    let extension = |name: &str| material.get("extensions").and_then(|extensions| extensions.get(name));
+
+    // the factors are already linear, unlike the images
+    let base_factor = color_member(pbr, "baseColorFactor")?.unwrap_or(WHITE);
+    let base_color: Arc<dyn Texture> = match pbr.and_then(|pbr| pbr.get("baseColorTexture")) {
+	Some(texture_info) => {
+	    let image: Arc<dyn Texture> = Arc::new(load_texture(document, texture_info, directory)?);
+	    if base_factor.r == 1f32 && base_factor.g == 1f32 && base_factor.b == 1f32 {
+		image
+	    } else {
+		Arc::new(ProductTexture { first: image, second: Arc::new(base_factor) })
+	    }
+	},
+	None => Arc::new(base_factor),
+    };
+
+    let mut principled = Principled::new(base_color);
+    principled.metallic = fraction_member(pbr, "metallicFactor")?.unwrap_or(1f32);
+    principled.roughness = fraction_member(pbr, "roughnessFactor")?.unwrap_or(1f32);
+
+    if let Some(clearcoat) = extension("KHR_materials_clearcoat") {
+	principled.clearcoat = fraction_member(Some(clearcoat), "clearcoatFactor")?.unwrap_or(0f32);
+	principled.clearcoat_roughness = fraction_member(Some(clearcoat), "clearcoatRoughnessFactor")?.unwrap_or(0f32);
+    }
+    if let Some(sheen) = extension("KHR_materials_sheen") {
+	principled.sheen = color_member(Some(sheen), "sheenColorFactor")?.unwrap_or(BLACK);
+    }
+    if let Some(transmission) = extension("KHR_materials_transmission") {
+	principled.transmission = fraction_member(Some(transmission), "transmissionFactor")?.unwrap_or(0f32);
+    }
+    if let Some(ior) = extension("KHR_materials_ior") {
+	principled.ior = number_member(Some(ior), "ior")?.unwrap_or(1.5f32);
+	if principled.ior <= 0f32 {
+	    return Err(String::from("the index of refraction must be positive"));
+	}
+    }
+    if let Some(specular) = extension("KHR_materials_specular") {
+	principled.specular = 0.5f32 * fraction_member(Some(specular), "specularFactor")?.unwrap_or(1f32);
+    }
+    if let Some(anisotropy) = extension("KHR_materials_anisotropy") {
+	principled.anisotropy = fraction_member(Some(anisotropy), "anisotropyStrength")?.unwrap_or(0f32);
+    }
+
+    let strength = match extension("KHR_materials_emissive_strength") {
+	Some(emissive_strength) => match number_member(Some(emissive_strength), "emissiveStrength")? {
+	    Some(strength) if strength < 0f32 => return Err(String::from("emissiveStrength can not be negative")),
+	    strength => strength.unwrap_or(1f32),
+	},
+	None => 1f32,
+    };
+    let emission = color_member(Some(material), "emissiveFactor")?.unwrap_or(BLACK) * strength;
+
+    Ok((principled, emission))
+}
+
+// follows the texture info through the textures and images to the image file, with the wrap mode of its sampler
+fn load_texture(document: &Json, texture_info: &Json, directory: &Path) -> Result<ImageTexture, String> {
+    let element = |array: &str, index: Option<&Json>| -> Result<&Json, String> {
+	index.and_then(Json::as_usize)
+	    .and_then(|index| document.get(array).and_then(Json::as_array).and_then(|elements| elements.get(index)))
+	    .ok_or_else(|| format!("invalid reference into {}", array))
+    };
+
+    let texture = element("textures", texture_info.get("index"))?;
+    let image = element("images", texture.get("source"))?;
+    let uri = match image.get("uri").and_then(Json::as_str) {
+	Some(uri) if !uri.starts_with("data:") => uri,
+	_ => return Err(String::from("only images in separate files are supported")),
+    };
+
+    let wrap_mode = match texture.get("sampler") {
+	Some(sampler) => match element("samplers", Some(sampler))?.get("wrapS").and_then(Json::as_usize) {
+	    Some(33071) => WrapMode::Clamp,
+	    Some(33648) => WrapMode::Mirror,
+	    _ => WrapMode::Repeat,
+	},
+	None => WrapMode::Repeat,
+    };
+
+    let path = directory.join(uri);
+    ImageTexture::load(&path.to_string_lossy(), wrap_mode)
+}
+
+fn number_member(object: Option<&Json>, key: &str) -> Result<Option<f32>, String> {
+    match object.and_then(|object| object.get(key)) {
+	Some(value) => match value.as_f32() {
+	    Some(number) => Ok(Some(number)),
+	    None => Err(format!("{} must be a number", key)),
+	},
+	None => Ok(None),
+    }
+}
+
+// like the same properties in scene files, the factors that weigh the parts of the material must be between 0 and 1
+fn fraction_member(object: Option<&Json>, key: &str) -> Result<Option<f32>, String> {
+    match number_member(object, key)? {
+	Some(number) if !(0f32 ..= 1f32).contains(&number) => Err(format!("{} must be between 0 and 1", key)),
+	number => Ok(number),
+    }
+}
+
+// from an array of three or four numbers, none of them negative, the alpha channel is ignored
+fn color_member(object: Option<&Json>, key: &str) -> Result<Option<Color>, String> {
+    let value = match object.and_then(|object| object.get(key)) {
+	Some(value) => value,
+	None => return Ok(None),
+    };
+
+    let numbers: Option<Vec<f32>> = value.as_array().map(|elements| elements.iter().map(Json::as_f32).collect()).unwrap_or(None);
+    match numbers.as_deref() {
+	Some([r, g, b]) | Some([r, g, b, _]) if *r >= 0f32 && *g >= 0f32 && *b >= 0f32 => Ok(Some(Color { r: *r, g: *g, b: *b })),
+	Some([_, _, _]) | Some([_, _, _, _]) => Err(format!("{} can not be negative", key)),
+	_ => Err(format!("{} must be an array of three or four numbers", key)),
+    }
+}
@@ -0,0 +1,110 @@
+use crate::color::*;
+use crate::surface_element::*;
+use crate::texture::*;
+
+// what happens to texture coordinates outside of [0, 1]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WrapMode {
+    Repeat, // the image is tiled
+    Mirror, // the image is tiled, with every other tile flipped so the edges line up
+    Clamp,  // the pixels at the edges are stretched out
+}
+
+impl WrapMode {
+    // names as used in scene files
+    pub const NAMES: [&'static str; 3] = ["repeat", "mirror", "clamp"];
+
+    pub fn from_name(name: &str) -> Option<WrapMode> {
+	match name {
+	    "repeat" => Some(WrapMode::Repeat),
+	    "mirror" => Some(WrapMode::Mirror),
+	    "clamp"  => Some(WrapMode::Clamp),
+	    _ => None,
+	}
+    }
+
+    // maps a pixel coordinate, which can be outside of the image, to one inside
+    fn apply(self, coordinate: i64, size: usize) -> usize {
+	let size = size as i64;
+	match self {
+	    WrapMode::Repeat => coordinate.rem_euclid(size) as usize,
+	    WrapMode::Mirror => {
+		let period = coordinate.rem_euclid(2 * size);
+		(if period < size { period } else { 2 * size - 1 - period }) as usize
+	    },
+	    WrapMode::Clamp => coordinate.clamp(0, size - 1) as usize,
+	}
+    }
+}
+
+// image looked up by texture coordinates, with u going right and v going up from the bottom left corner
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>, // linear, row by row from the top
+    pub wrap_mode: WrapMode,
+}
+
+impl ImageTexture {
+    // png, jpeg and the other formats supported by the image crate, which are taken to be in srgb
+    pub fn load(path: &str, wrap_mode: WrapMode) -> Result<ImageTexture, String> {
+	println!("loading: {}", path);
+
+	let image = image::open(path).map_err(|error| format!("couldn't load the image {}: {}", path, error))?.to_rgb8();
+
+	let (width, height) = image.dimensions();
+	if width == 0 || height == 0 {
+	    return Err(format!("the image {} is empty", path));
+	}
+
+	// decoded once for all 256 values, rather than for every pixel
+	let mut linear = [0f32; 256];
+	for (value, decoded) in linear.iter_mut().enumerate() {
+	    *decoded = srgb_to_linear(value as f32 / 255f32);
+	}
+
+	let pixels = image.pixels().map(|pixel| Color {
+	    r: linear[pixel[0] as usize],
+	    g: linear[pixel[1] as usize],
+	    b: linear[pixel[2] as usize],
+	}).collect();
+
+	Ok(ImageTexture {
+	    width: width as usize,
+	    height: height as usize,
+	    pixels,
+	    wrap_mode,
+	})
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> Color {
+	let x = self.wrap_mode.apply(x, self.width);
+	let y = self.wrap_mode.apply(y, self.height);
+	self.pixels[x + y * self.width]
+    }
+
+    // bilinear interpolation between the four pixels nearest to the texture coordinates
+    pub fn lookup(&self, (u, v): (f32, f32)) -> Color {
+	// pixel centers are at half integers
+	let x = u * self.width as f32 - 0.5f32;
+	let y = (1f32 - v) * self.height as f32 - 0.5f32;
+
+	let x0 = x.floor();
+	let y0 = y.floor();
+	let fx = x - x0;
+	let fy = y - y0;
+	let (x0, y0) = (x0 as i64, y0 as i64);
+
+	let top    = self.pixel(x0, y0)     * (1f32 - fx) + self.pixel(x0 + 1, y0)     * fx;
+	let bottom = self.pixel(x0, y0 + 1) * (1f32 - fx) + self.pixel(x0 + 1, y0 + 1) * fx;
+
+	top * (1f32 - fy) + bottom * fy
+    }
+}
+
+impl Texture for ImageTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	self.lookup(surface_element.texture_coordinates)
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use crate::vector::*;
+use crate::triangle::*;
+use crate::shape::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bvh::*;
+use crate::bounding_box::*;
+use crate::transform::*;
+
+// triangles with their own bvh, in their own coordinate system, shared by all instances placing them in the scene
+#[derive(Debug)]
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+    pub bvh: Bvh,
+    pub bounds: BoundingBox,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Mesh {
+	let triangle_bounds: Vec<BoundingBox> = triangles.iter().map(|triangle| triangle.bounds()).collect();
+	let bounds = triangle_bounds.iter().fold(BoundingBox::empty(), |bounds, triangle| bounds.union(*triangle));
+
+	Mesh {
+	    bvh: Bvh::build(&triangle_bounds),
+	    triangles,
+	    bounds,
+	}
+    }
+
+    // only hits for which accept returns true count
+    pub fn intersect<F: Fn(&SurfaceElement) -> bool>(&self, ray: Ray, accept: F) -> Option<(f32, SurfaceElement)> {
+	self.bvh.closest_hit(ray, |index| self.triangles[index].intersect(ray).filter(|(_, surface_element)| accept(surface_element)))
+    }
+
+    // whether the ray hits any triangle, accept is given the triangle that was hit
+    pub fn occludes<F: Fn(&Triangle) -> bool>(&self, ray: Ray, accept: F) -> bool {
+	self.bvh.any_hit(ray, |index| {
+	    let triangle = &self.triangles[index];
+	    triangle.intersect_barycentric(ray).is_some() && accept(triangle)
+	})
+    }
+}
+
+// a mesh placed in the scene
+#[derive(Clone, Debug)]
+pub struct Instance {
+    pub mesh: Arc<Mesh>,
+    pub transform: Transform, // from the coordinates of the mesh to those of the scene
+    pub material: Option<usize>, // replaces the materials of all triangles of the mesh
+}
+
+impl Instance {
+    pub fn bounds(&self) -> BoundingBox {
+	self.transform.apply_bounds(self.mesh.bounds)
+    }
+
+    // the triangles of the mesh, moved into the scene
+    pub fn world_triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+	let mirrors = self.transform.mirrors();
+
+	self.mesh.triangles.iter().map(move |triangle| {
+	    let vertices = counterclockwise(triangle.vertices, mirrors).map(|vertex| self.transform.apply_point(vertex));
+
+	    let normals = triangle.normals.map(|normals| {
+		counterclockwise(normals, mirrors).map(|normal| self.transform.apply_normal(normal).normalised())
+	    });
+
+	    // the default texture coordinates would be swapped with the corners
+	    let texture_coordinates = triangle.texture_coordinates.unwrap_or([(0f32, 0f32), (1f32, 0f32), (0f32, 1f32)]);
+
+	    Triangle {
+		vertices,
+		normals,
+		texture_coordinates: Some(counterclockwise(texture_coordinates, mirrors)),
+		colors: triangle.colors.map(|colors| counterclockwise(colors, mirrors)),
+		material: self.material.unwrap_or(triangle.material),
+	    }
+	})
+    }
+
+    // accept is given the surface element in the coordinates of the mesh, but with the material of the instance
+    pub fn intersect<F: Fn(&SurfaceElement, Ray) -> bool>(&self, ray: Ray, accept: F) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, length, shift) = self.local_ray(ray);
+
+	let material = |surface_element: &SurfaceElement| self.material.unwrap_or(surface_element.material);
+	let (local_depth, local_surface_element) = self.mesh.intersect(local_ray, |surface_element| {
+	    accept(&SurfaceElement { material: material(surface_element), ..*surface_element }, local_ray)
+	})?;
+
+	// the ray hits the front of the triangles in the coordinates of the mesh, and the transformed normal
+	// keeps facing the ray, even when the transform mirrors
+	let surface_element = SurfaceElement {
+	    position: self.transform.apply_point(local_surface_element.position),
+	    error: self.transform.apply_point_error(local_surface_element.position, local_surface_element.error),
+	    normal: self.transform.apply_normal(local_surface_element.normal).normalised(),
+	    geometric_normal: self.transform.apply_normal(local_surface_element.geometric_normal).normalised(),
+	    tangent: self.transform.apply_vector(local_surface_element.tangent).normalised(),
+	    material: material(&local_surface_element),
+	    ..local_surface_element
+	};
+
+	Some(((local_depth + shift) / length, surface_element))
+    }
+
+    // whether the ray hits any of the triangles, accept is given the geometric normal and material of the triangle
+    // that was hit, and the ray, all in the coordinates of the mesh
+    pub fn occludes<F: Fn(Vector, usize, Ray) -> bool>(&self, ray: Ray, accept: F) -> bool {
+	let (local_ray, _, _) = self.local_ray(ray);
+	self.mesh.occludes(local_ray, |triangle| accept(triangle.normal(), self.material.unwrap_or(triangle.material), local_ray))
+    }
+
+    // the ray moved into the coordinates of the mesh, where scaling changes the length of its direction,
+    // depths along the normalised direction there are divided by that length to get depths in the scene,
+    // after adding the shift the origin was moved forward by
+    fn local_ray(&self, ray: Ray) -> (Ray, f32, f32) {
+	let inverse = self.transform.inverse();
+	let direction = inverse.apply_vector(ray.direction);
+	let length = direction.norm();
+	let direction = direction * (1f32 / length);
+
+	// rounding in the transform could move the origin back onto the surface the ray leaves,
+	// so it is moved forward along the ray by as much as the rounding could have moved it
+	let origin = inverse.apply_point(ray.origin);
+	let shift = dot(direction.abs(), inverse.apply_point_error(ray.origin, Vector { x: 0f32, y: 0f32, z: 0f32 }));
+
+	let local_ray = Ray {
+	    origin: origin + direction * shift,
+	    direction,
+	    t_min: (ray.t_min * length - shift).max(0f32),
+	    t_max: ray.t_max * length - shift,
+	};
+
+	(local_ray, length, shift)
+    }
+}
+
+// mirroring turns triangles inside out, swapping two of their corners keeps them facing outwards
+fn counterclockwise<T>([c0, c1, c2]: [T; 3], mirrors: bool) -> [T; 3] {
+    if mirrors {
+	[c0, c2, c1]
+    } else {
+	[c0, c1, c2]
+    }
+}
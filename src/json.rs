@@ -0,0 +1,200 @@
+// a small json parser, enough for reading the descriptions in gltf files
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(HashMap<String, Json>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+	let mut chars = text.chars().peekable();
+	let value = parse_value(&mut chars)?;
+	skip_whitespace(&mut chars);
+	match chars.next() {
+	    None => Ok(value),
+	    Some(c) => Err(format!("unexpected '{}' after the end of the document", c)),
+	}
+    }
+
+    // the member with the given key, None when this is not an object or has no such member
+    pub fn get(&self, key: &str) -> Option<&Json> {
+	match self {
+	    Json::Object(members) => members.get(key),
+	    _ => None,
+	}
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+	match self {
+	    Json::Number(number) => Some(*number as f32),
+	    _ => None,
+	}
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+	match self {
+	    Json::Number(number) if *number >= 0f64 && number.fract() == 0f64 => Some(*number as usize),
+	    _ => None,
+	}
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+	match self {
+	    Json::String(string) => Some(string),
+	    _ => None,
+	}
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+	match self {
+	    Json::Array(elements) => Some(elements),
+	    _ => None,
+	}
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+	chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    skip_whitespace(chars);
+    match chars.next() {
+	Some(c) if c == expected => Ok(()),
+	Some(c) => Err(format!("expected '{}' but found '{}'", expected, c)),
+	None => Err(format!("expected '{}' but the document ended", expected)),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+	Some('{') => parse_object(chars),
+	Some('[') => parse_array(chars),
+	Some('"') => Ok(Json::String(parse_string(chars)?)),
+	Some('t') => parse_literal(chars, "true", Json::Bool(true)),
+	Some('f') => parse_literal(chars, "false", Json::Bool(false)),
+	Some('n') => parse_literal(chars, "null", Json::Null),
+	Some(c) if *c == '-' || c.is_ascii_digit() => parse_number(chars),
+	Some(c) => Err(format!("unexpected '{}'", c)),
+	None => Err(String::from("the document ended where a value was expected")),
+    }
+}
+
+fn parse_object(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    expect(chars, '{')?;
+    let mut members = HashMap::new();
+
+    skip_whitespace(chars);
+    if chars.peek() == Some(&'}') {
+	chars.next();
+	return Ok(Json::Object(members));
+    }
+
+    loop {
+	skip_whitespace(chars);
+	let key = parse_string(chars)?;
+	expect(chars, ':')?;
+	members.insert(key, parse_value(chars)?);
+
+	skip_whitespace(chars);
+	match chars.next() {
+	    Some(',') => {},
+	    Some('}') => return Ok(Json::Object(members)),
+	    _ => return Err(String::from("expected ',' or '}' in an object")),
+	}
+    }
+}
+
+fn parse_array(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    expect(chars, '[')?;
+    let mut elements = Vec::new();
+
+    skip_whitespace(chars);
+    if chars.peek() == Some(&']') {
+	chars.next();
+	return Ok(Json::Array(elements));
+    }
+
+    loop {
+	elements.push(parse_value(chars)?);
+
+	skip_whitespace(chars);
+	match chars.next() {
+	    Some(',') => {},
+	    Some(']') => return Ok(Json::Array(elements)),
+	    _ => return Err(String::from("expected ',' or ']' in an array")),
+	}
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut string = String::new();
+
+    loop {
+	match chars.next() {
+	    Some('"') => return Ok(string),
+	    Some('\\') => {
+		let escaped = match chars.next() {
+		    Some('"') => '"',
+		    Some('\\') => '\\',
+		    Some('/') => '/',
+		    Some('b') => '\u{8}',
+		    Some('f') => '\u{c}',
+		    Some('n') => '\n',
+		    Some('r') => '\r',
+		    Some('t') => '\t',
+		    Some('u') => {
+			let digits: String = chars.by_ref().take(4).collect();
+			let code = u32::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape '\\u{}'", digits))?;
+			// surrogate pairs are not combined, the names in gltf files rarely need them
+			char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+		    },
+		    Some(c) => return Err(format!("invalid escape '\\{}'", c)),
+		    None => return Err(String::from("the document ended inside a string")),
+		};
+		string.push(escaped);
+	    },
+	    Some(c) => string.push(c),
+	    None => return Err(String::from("the document ended inside a string")),
+	}
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+	if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+	    text.push(c);
+	    chars.next();
+	} else {
+	    break;
+	}
+    }
+
+    match text.parse::<f64>() {
+	Ok(number) => Ok(Json::Number(number)),
+	Err(_) => Err(format!("invalid number '{}'", text)),
+    }
+}
+
+fn parse_literal(chars: &mut Peekable<Chars>, literal: &str, value: Json) -> Result<Json, String> {
+    for expected in literal.chars() {
+	if chars.next() != Some(expected) {
+	    return Err(format!("expected '{}'", literal));
+	}
+    }
+    Ok(value)
+}
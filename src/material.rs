@@ -1,6 +1,30 @@
+use std::sync::Arc;
+
 use crate::color::*;
+use crate::texture::*;
+use crate::bsdf::*;
+use crate::surface_element::*;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Material {
-    pub diffuse_color: Color,
+    pub bsdf: Arc<dyn Bsdf>, // how the surface reflects or transmits light
+    pub emission: Color, // light emitted from the front of the surface
+}
+
+impl Material {
+    pub fn is_emissive(&self) -> bool {
+	self.emission.r > 0f32 || self.emission.g > 0f32 || self.emission.b > 0f32
+    }
+
+    pub fn is_two_sided(&self) -> bool {
+	self.bsdf.is_two_sided()
+    }
+}
+
+// looks up the texture at the surface element, where vertex colors take precedence over it
+pub fn surface_color(texture: &dyn Texture, surface_element: &SurfaceElement) -> Color {
+    match surface_element.color {
+	Some(color) => color,
+	None => texture.color(surface_element),
+    }
 }
@@ -0,0 +1,147 @@
+// the ggx (trowbridge-reitz) microfacet distribution, in a local frame with the surface normal along z
+// alpha is the width of the distribution, zero for a perfectly smooth surface
+
+use std::f32::consts::PI;
+
+use crate::vector::*;
+
+// below this the surface is taken to be perfectly smooth
+pub const MIN_ALPHA: f32 = 0.0001f32;
+
+// auxiliary function of the smith masking function, for the direction w
+fn smith_lambda(w: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    let cos2 = w.z * w.z;
+    if cos2 == 0f32 {
+	return f32::INFINITY;
+    }
+    let alpha2_tan2 = (alpha_x * alpha_x * w.x * w.x + alpha_y * alpha_y * w.y * w.y) / cos2;
+
+    0.5f32 * (-1f32 + (1f32 + alpha2_tan2).sqrt())
+}
+
+// density of the microfacet normals h, per unit of area of the surface
+pub fn ggx_distribution(h: Vector, alpha: f32) -> f32 {
+    ggx_distribution_anisotropic(h, alpha, alpha)
+}
+
+// the same with the width alpha_x along the x axis of the local frame and alpha_y along the y axis
+pub fn ggx_distribution_anisotropic(h: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    if h.z <= 0f32 {
+	return 0f32;
+    }
+    let t = h.x * h.x / (alpha_x * alpha_x) + h.y * h.y / (alpha_y * alpha_y) + h.z * h.z;
+
+    1f32 / (PI * alpha_x * alpha_y * t * t)
+}
+
+// fraction of the microfacets with normal h that is visible from w
+pub fn smith_g1(w: Vector, alpha: f32) -> f32 {
+    smith_g1_anisotropic(w, alpha, alpha)
+}
+
+pub fn smith_g1_anisotropic(w: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    1f32 / (1f32 + smith_lambda(w, alpha_x, alpha_y))
+}
+
+// fraction of the microfacets visible from both wo and wi, taking into account that these are correlated
+pub fn smith_g2(wo: Vector, wi: Vector, alpha: f32) -> f32 {
+    smith_g2_anisotropic(wo, wi, alpha, alpha)
+}
+
+pub fn smith_g2_anisotropic(wo: Vector, wi: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    1f32 / (1f32 + smith_lambda(wo, alpha_x, alpha_y) + smith_lambda(wi, alpha_x, alpha_y))
+}
+
+// fraction of the light reflected at the boundary between two dielectrics, averaged over both polarisations,
+// eta is the index of refraction on the other side divided by that on the side of the light
+pub fn fresnel_dielectric(cos_theta: f32, eta: f32) -> f32 {
+    let cos_i = cos_theta.clamp(0f32, 1f32);
+    let sin2_t = (1f32 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1f32 {
+	return 1f32; // total internal reflection
+    }
+    let cos_t = (1f32 - sin2_t).sqrt();
+
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+
+    0.5f32 * (rs * rs + rp * rp)
+}
+
+// samples a microfacet normal from the distribution of normals visible from wo, which must be above the surface,
+// as described by eric heitz in "sampling the ggx distribution of visible normals" (2018)
+pub fn sample_visible_normal(wo: Vector, alpha: f32, p1: f32, p2: f32) -> Vector {
+    sample_visible_normal_anisotropic(wo, alpha, alpha, p1, p2)
+}
+
+pub fn sample_visible_normal_anisotropic(wo: Vector, alpha_x: f32, alpha_y: f32, p1: f32, p2: f32) -> Vector {
+    // stretch the view direction so the distribution becomes that of a hemisphere
+    let stretched = Vector { x: alpha_x * wo.x, y: alpha_y * wo.y, z: wo.z }.normalised();
+
+    // orthonormal basis around the stretched direction
+    let length2 = stretched.x * stretched.x + stretched.y * stretched.y;
+    let t1 = if length2 > 0f32 {
+	Vector { x: -stretched.y, y: stretched.x, z: 0f32 } * (1f32 / length2.sqrt())
+    } else {
+	Vector { x: 1f32, y: 0f32, z: 0f32 }
+    };
+    let t2 = cross(stretched, t1);
+
+    // uniform point on a disc, squeezed onto the part of the hemisphere that is visible
+    let r = p1.sqrt();
+    let phi = 2f32 * PI * p2;
+    let d1 = r * phi.cos();
+    let s = 0.5f32 * (1f32 + stretched.z);
+    let d2 = (1f32 - s) * (1f32 - d1 * d1).sqrt() + s * r * phi.sin();
+
+    let hemisphere_normal = t1 * d1 + t2 * d2 + stretched * (1f32 - d1 * d1 - d2 * d2).max(0f32).sqrt();
+
+    // and unstretch
+    Vector {
+	x: alpha_x * hemisphere_normal.x,
+	y: alpha_y * hemisphere_normal.y,
+	z: hemisphere_normal.z.max(0f32),
+    }.normalised()
+}
+
+// density of sample_visible_normal picking h
+pub fn visible_normal_pdf(wo: Vector, h: Vector, alpha: f32) -> f32 {
+    visible_normal_pdf_anisotropic(wo, h, alpha, alpha)
+}
+
+pub fn visible_normal_pdf_anisotropic(wo: Vector, h: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    smith_g1_anisotropic(wo, alpha_x, alpha_y) * dot(wo, h).max(0f32) * ggx_distribution_anisotropic(h, alpha_x, alpha_y) / wo.z
+}
+
+pub fn reflect(w: Vector, normal: Vector) -> Vector {
+    normal * (2f32 * dot(w, normal)) - w
+}
+
+// w points away from the surface on the same side as the normal, eta is the index of refraction
+// on the other side divided by that on the side of w, None for total internal reflection
+pub fn refract(w: Vector, normal: Vector, eta: f32) -> Option<Vector> {
+    let cos_i = dot(w, normal);
+    let sin2_t = (1f32 - cos_i * cos_i).max(0f32) / (eta * eta);
+    if sin2_t >= 1f32 {
+	return None;
+    }
+    let cos_t = (1f32 - sin2_t).sqrt();
+
+    Some(-w * (1f32 / eta) + normal * (cos_i / eta - cos_t))
+}
+
+// the microfacet normal that refracts wi into wo, which is above the surface while wi is below it,
+// eta being the index of refraction on the side of wi divided by that on the side of wo, None when there is none
+pub fn refraction_normal(wo: Vector, wi: Vector, eta: f32) -> Option<Vector> {
+    let half = wo + wi * eta;
+    if half.norm2() == 0f32 {
+	return None;
+    }
+    let half = half.normalised();
+    let normal = if half.z < 0f32 { -half } else { half };
+
+    if dot(wo, normal) <= 0f32 || dot(wi, normal) >= 0f32 {
+	return None;
+    }
+    Some(normal)
+}
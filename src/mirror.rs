@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::texture::*;
+use crate::surface_element::*;
+use crate::material::*;
+use crate::bsdf::*;
+
+// perfect specular reflection, tinted by the texture
+#[derive(Debug)]
+pub struct Mirror {
+    pub texture: Arc<dyn Texture>,
+}
+
+impl Bsdf for Mirror {
+    fn eval(&self, _surface_element: &SurfaceElement, _wo: Vector, _wi: Vector) -> Color {
+	BLACK
+    }
+
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, _u: [f32; 3]) -> Option<BsdfSample> {
+	if wo.z <= 0f32 {
+	    return None;
+	}
+
+	Some(BsdfSample {
+	    wi: Vector { x: -wo.x, y: -wo.y, z: wo.z },
+	    weight: surface_color(self.texture.as_ref(), surface_element),
+	    pdf: 1f32,
+	})
+    }
+
+    fn pdf(&self, _surface_element: &SurfaceElement, _wo: Vector, _wi: Vector) -> f32 {
+	0f32
+    }
+
+    fn is_specular(&self) -> bool {
+	true
+    }
+}
@@ -2,51 +2,126 @@ use std::fs;
 
 use crate::vector::*;
 use crate::triangle::*;
-use crate::material::*;
+use crate::shape::*;
+use crate::obj::*;
+use crate::ply::*;
 use crate::color::*;
+use crate::transform::*;
 
 #[derive(Clone,Debug)]
 pub struct RawModel {
-    vertices: Vec<Vector>,
-    triangle_indices: Vec<(usize, usize, usize)>,
+    pub vertices: Vec<Vector>,
+    pub normals: Vec<Vector>,
+    pub texture_coordinates: Vec<(f32, f32)>,
+    pub colors: Vec<Color>, // either empty or one for every vertex
+    pub triangle_indices: Vec<(usize, usize, usize)>,
+    // both have one entry per triangle, None when that triangle does not have them
+    pub normal_indices: Vec<Option<(usize, usize, usize)>>,
+    pub texture_coordinate_indices: Vec<Option<(usize, usize, usize)>>,
+    pub groups: Vec<Group>, // ordered by first_triangle
+}
+
+// named part of a model, such as an object or group in an obj file
+#[derive(Clone,Debug)]
+pub struct Group {
+    pub name: String,
+    pub first_triangle: usize, // the group continues up to the first triangle of the next group
 }
 
 impl RawModel {
-    pub fn load_to_raw(model_name: &str) -> RawModel {
+    pub fn empty() -> RawModel {
+	RawModel {
+	    vertices: Vec::new(),
+	    normals: Vec::new(),
+	    texture_coordinates: Vec::new(),
+	    colors: Vec::new(),
+	    triangle_indices: Vec::new(),
+	    normal_indices: Vec::new(),
+	    texture_coordinate_indices: Vec::new(),
+	    groups: Vec::new(),
+	}
+    }
+
+    // files ending in .obj are read as wavefront obj and files ending in .ply as polygon file format,
+    // anything else is the name of a model in the models directory
+    pub fn load(name: &str) -> Result<RawModel, String> {
+	let lowercase = name.to_lowercase();
+	if lowercase.ends_with(".obj") {
+	    load_obj(name)
+	} else if lowercase.ends_with(".ply") {
+	    load_ply(name)
+	} else {
+	    RawModel::load_to_raw(name)
+	}
+    }
+
+    // keeps only the triangles in the groups with the given name
+    pub fn select_group(self, name: &str) -> Result<RawModel, String> {
+	let mut selected = RawModel {
+	    vertices: self.vertices,
+	    normals: self.normals,
+	    texture_coordinates: self.texture_coordinates,
+	    colors: self.colors,
+	    triangle_indices: Vec::new(),
+	    normal_indices: Vec::new(),
+	    texture_coordinate_indices: Vec::new(),
+	    groups: Vec::new(),
+	};
+
+	for (n, group) in self.groups.iter().enumerate() {
+	    if group.name != name {
+		continue;
+	    }
+
+	    let end = match self.groups.get(n + 1) {
+		Some(next_group) => next_group.first_triangle,
+		None => self.triangle_indices.len(),
+	    };
+
+	    selected.groups.push(Group {
+		name: group.name.clone(),
+		first_triangle: selected.triangle_indices.len(),
+	    });
+	    selected.triangle_indices.extend_from_slice(&self.triangle_indices[group.first_triangle .. end]);
+	    selected.normal_indices.extend_from_slice(&self.normal_indices[group.first_triangle .. end]);
+	    selected.texture_coordinate_indices.extend_from_slice(&self.texture_coordinate_indices[group.first_triangle .. end]);
+	}
+
+	if selected.groups.is_empty() {
+	    return Err(format!("the model has no group named '{}'", name));
+	}
+
+	Ok(selected)
+    }
+
+    // loads models/<model_name>/vertices.txt and models/<model_name>/triangles.txt
+    pub fn load_to_raw(model_name: &str) -> Result<RawModel, String> {
 	println!("loading: {}", model_name);
 
 	let mut relative_path = String::from("models/");
 	relative_path.push_str(model_name);
 
-	let mut raw_model = RawModel {
-	    vertices: Vec::new(),
-	    triangle_indices: Vec::new(),
-	};
+	let mut raw_model = RawModel::empty();
 
 	{ // load vertices
 	    let mut vertices_path = relative_path.clone();
 	    vertices_path.push_str("/vertices.txt");
 
-	    let vertices_file = fs::read_to_string(vertices_path.as_str()).expect(format!("couldn't read the file {}", vertices_path).as_str());
+	    let vertices_file = fs::read_to_string(vertices_path.as_str()).map_err(|error| format!("couldn't read the file {}: {}", vertices_path, error))?;
+
+	    for (line_number, line) in vertices_file.lines().enumerate() {
+		let error = |what: &str| format!("{} line {}: couldn't read {}", vertices_path, line_number + 1, what);
 
-	    for line in vertices_file.lines() {
 		let mut words = line.split_whitespace();
-		let x_string = words.next().expect("couldn't read x");
-		let y_string = words.next().expect("couldn't read y");
-		let z_string = words.next().expect("couldn't read z");
-
-		let x: f32 = x_string.parse().expect("couldn't parse x");
-		let y: f32 = y_string.parse().expect("couldn't parse y");
-		let z: f32 = z_string.parse().expect("couldn't parse z");
-
-		// TEMP y and z flipped TEMP <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-		let new_vertex = Vector {
-		    x: x * 5f32,
-		    y: -z * 5f32 + 0.2f32,
-		    z: y * 5f32 - 0.2f32,
-		};
-
-		raw_model.vertices.push(new_vertex);
+		let x_string = words.next().ok_or_else(|| error("x"))?;
+		let y_string = words.next().ok_or_else(|| error("y"))?;
+		let z_string = words.next().ok_or_else(|| error("z"))?;
+
+		let x: f32 = x_string.parse().map_err(|_| error("x"))?;
+		let y: f32 = y_string.parse().map_err(|_| error("y"))?;
+		let z: f32 = z_string.parse().map_err(|_| error("z"))?;
+
+		raw_model.vertices.push(Vector { x, y, z });
 	    }
 	}
 	
@@ -54,65 +129,184 @@ impl RawModel {
 	    let mut triangles_path = relative_path.clone();
 	    triangles_path.push_str("/triangles.txt");
 
-	    let triangles_file = fs::read_to_string(triangles_path.as_str()).expect(format!("couldn't read the file {}", triangles_path).as_str());
+	    let triangles_file = fs::read_to_string(triangles_path.as_str()).map_err(|error| format!("couldn't read the file {}: {}", triangles_path, error))?;
+
+	    for (line_number, line) in triangles_file.lines().enumerate() {
+		let error = |what: &str| format!("{} line {}: couldn't read {}", triangles_path, line_number + 1, what);
 
-	    for line in triangles_file.lines() {
 		let mut words = line.split_whitespace();
-		let n1_string = words.next().expect("couldn't read n1");
-		let n2_string = words.next().expect("couldn't read n2");
-		let n3_string = words.next().expect("couldn't read n3");
+		let n1_string = words.next().ok_or_else(|| error("n1"))?;
+		let n2_string = words.next().ok_or_else(|| error("n2"))?;
+		let n3_string = words.next().ok_or_else(|| error("n3"))?;
+
+		let n1: usize = n1_string.parse().map_err(|_| error("n1"))?;
+		let n2: usize = n2_string.parse().map_err(|_| error("n2"))?;
+		let n3: usize = n3_string.parse().map_err(|_| error("n3"))?;
 
-		let n1: usize = n1_string.parse().expect("couldn't parse n1");
-		let n2: usize = n2_string.parse().expect("couldn't parse n2");
-		let n3: usize = n3_string.parse().expect("couldn't parse n3");
+		let num_vertices = raw_model.vertices.len();
+		if n1 >= num_vertices || n2 >= num_vertices || n3 >= num_vertices {
+		    return Err(format!("{} line {}: vertex index out of range", triangles_path, line_number + 1));
+		}
 
 		raw_model.triangle_indices.push((n1,n2,n3));
+		raw_model.normal_indices.push(None);
+		raw_model.texture_coordinate_indices.push(None);
 	    }
 	}
 	
-	raw_model
+	Ok(raw_model)
     }
 }
 
+// which axis points up in a model file, the scene itself has z pointing up
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+// places a model in the scene, applied in the order of the fields
+#[derive(Copy, Clone, Debug)]
+pub struct ModelTransform {
+    pub up_axis: UpAxis,
+    pub scale: Vector,       // per axis
+    pub rotation: Vector,    // in degrees, around the x, then the y and then the z axis
+    pub translation: Vector,
+}
+
+impl ModelTransform {
+    // leaves a z-up model as is
+    pub fn identity() -> ModelTransform {
+	ModelTransform {
+	    up_axis: UpAxis::Z,
+	    scale: Vector { x: 1f32, y: 1f32, z: 1f32 },
+	    rotation: Vector { x: 0f32, y: 0f32, z: 0f32 },
+	    translation: Vector { x: 0f32, y: 0f32, z: 0f32 },
+	}
+    }
+
+    pub fn transform(self) -> Transform {
+	let up = match self.up_axis {
+	    UpAxis::Y => Transform::rotation(0, 90f32), // takes y to z and z to -y
+	    UpAxis::Z => Transform::identity(),
+	};
+
+	Transform::translation(self.translation) *
+	    Transform::rotation(2, self.rotation.z) *
+	    Transform::rotation(1, self.rotation.y) *
+	    Transform::rotation(0, self.rotation.x) *
+	    Transform::scaling(self.scale) *
+	    up
+    }
+}
+
+// where the vertex normals used for smooth shading come from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VertexNormals {
+    Flat,     // none, every triangle is shaded with its own normal
+    FromFile, // the normals in the model file, triangles without them are flat
+    Computed, // the normals in the model file, and for triangles without them the average of the surrounding triangles
+}
+
 pub struct Model {
     pub triangles: Vec<Triangle>,
 }
 
 impl Model {
-    pub fn from_raw(raw_model: RawModel) -> Model {
+    // material is an index into Scene::materials
+    pub fn from_raw(raw_model: RawModel, material: usize, transform: Transform, vertex_normals: VertexNormals) -> Model {
 	let mut model = Model {
 	    triangles: Vec::new(),
 	};
 
-	let default_material = Material {
-	    diffuse_color: Color {
-		r: 0.9f32,
-		g: 0.9f32,
-		b: 0.9f32,
-	    },
+	let vertices: Vec<Vector> = raw_model.vertices.iter().map(|&vertex| transform.apply_point(vertex)).collect();
+
+	// mirroring turns the triangles inside out, their corners then have to be reordered to keep facing outwards
+	let counterclockwise = |(n1, n2, n3): (usize, usize, usize)| {
+	    if transform.mirrors() {
+		(n1, n3, n2)
+	    } else {
+		(n1, n2, n3)
+	    }
 	};
-	
-	for triangle_n123 in raw_model.triangle_indices {
-	    let (n1, n2, n3) = triangle_n123;
 
-	    let v1_raw = raw_model.vertices[n1];
-	    let v2_raw = raw_model.vertices[n2];
-	    let v3_raw = raw_model.vertices[n3];
+	let computed_normals = if vertex_normals == VertexNormals::Computed {
+	    let triangles: Vec<(usize, usize, usize)> = raw_model.triangle_indices.iter().map(|&indices| counterclockwise(indices)).collect();
+	    compute_vertex_normals(&vertices, &triangles)
+	} else {
+	    Vec::new()
+	};
 
-	    let base = v1_raw;
-	    let v1 = v2_raw - v1_raw;
-	    let v2 = v3_raw - v1_raw;
+	for (n, &indices) in raw_model.triangle_indices.iter().enumerate() {
+	    let (n1, n2, n3) = counterclockwise(indices);
+
+	    let normals = match (vertex_normals, raw_model.normal_indices[n]) {
+		(VertexNormals::Flat, _) => None,
+		(_, Some(normal_indices)) => {
+		    let (m1, m2, m3) = counterclockwise(normal_indices);
+		    unit_normals([m1, m2, m3].map(|m| transform.apply_normal(raw_model.normals[m])))
+		},
+		(VertexNormals::FromFile, None) => None,
+		(VertexNormals::Computed, None) => unit_normals([computed_normals[n1], computed_normals[n2], computed_normals[n3]]),
+	    };
+
+	    let texture_coordinates = raw_model.texture_coordinate_indices[n].map(|indices| {
+		let (m1, m2, m3) = counterclockwise(indices);
+		[raw_model.texture_coordinates[m1], raw_model.texture_coordinates[m2], raw_model.texture_coordinates[m3]]
+	    });
+
+	    let colors = if raw_model.colors.is_empty() {
+		None
+	    } else {
+		Some([raw_model.colors[n1], raw_model.colors[n2], raw_model.colors[n3]])
+	    };
 
 	    let triangle = Triangle {
-		base: base,
-		v1: v1,
-		v2: v2,
-		material: default_material,
+		vertices: [vertices[n1], vertices[n2], vertices[n3]],
+		normals,
+		texture_coordinates,
+		colors,
+		material,
 	    };
 
-	    model.triangles.push(triangle);
+	    // degenerate triangles have no normal, and can never be hit anyway
+	    if triangle.area() > 0f32 {
+		model.triangles.push(triangle);
+	    }
 	}
 
 	model
     }
 }
+
+// sums the normals of the triangles around each vertex, weighed by the angle of the triangle at that vertex,
+// which unlike weighing by area does not depend on how the surface around the vertex happens to be divided into triangles
+fn compute_vertex_normals(vertices: &[Vector], triangles: &[(usize, usize, usize)]) -> Vec<Vector> {
+    let mut normals = vec![Vector { x: 0f32, y: 0f32, z: 0f32 }; vertices.len()];
+
+    for &(n1, n2, n3) in triangles {
+	let face_normal = cross(vertices[n2] - vertices[n1], vertices[n3] - vertices[n1]);
+	if face_normal.norm2() == 0f32 {
+	    continue;
+	}
+	let face_normal = face_normal.normalised();
+
+	for (corner, previous, next) in [(n1, n3, n2), (n2, n1, n3), (n3, n2, n1)] {
+	    let to_previous = vertices[previous] - vertices[corner];
+	    let to_next = vertices[next] - vertices[corner];
+	    let cos_angle = dot(to_previous, to_next) / (to_previous.norm() * to_next.norm());
+	    normals[corner] = normals[corner] + face_normal * cos_angle.clamp(-1f32, 1f32).acos();
+	}
+    }
+
+    normals
+}
+
+// None when any of the normals has no direction
+fn unit_normals(normals: [Vector; 3]) -> Option<[Vector; 3]> {
+    if normals.iter().all(|normal| normal.norm2() > 0f32) {
+	Some(normals.map(|normal| normal.normalised()))
+    } else {
+	None
+    }
+}
@@ -0,0 +1,100 @@
+// gradient noise as described by ken perlin in "improving noise" (2002), and sums of it over several octaves
+
+use crate::vector::*;
+
+// the permutation from the reference implementation, so the noise is the same on every run
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137,  91,  90,  15, 131,  13, 201,  95,  96,  53, 194, 233,   7, 225,
+    140,  36, 103,  30,  69, 142,   8,  99,  37, 240,  21,  10,  23, 190,   6, 148,
+    247, 120, 234,  75,   0,  26, 197,  62,  94, 252, 219, 203, 117,  35,  11,  32,
+     57, 177,  33,  88, 237, 149,  56,  87, 174,  20, 125, 136, 171, 168,  68, 175,
+     74, 165,  71, 134, 139,  48,  27, 166,  77, 146, 158, 231,  83, 111, 229, 122,
+     60, 211, 133, 230, 220, 105,  92,  41,  55,  46, 245,  40, 244, 102, 143,  54,
+     65,  25,  63, 161,   1, 216,  80,  73, 209,  76, 132, 187, 208,  89,  18, 169,
+    200, 196, 135, 130, 116, 188, 159,  86, 164, 100, 109, 198, 173, 186,   3,  64,
+     52, 217, 226, 250, 124, 123,   5, 202,  38, 147, 118, 126, 255,  82,  85, 212,
+    207, 206,  59, 227,  47,  16,  58,  17, 182, 189,  28,  42, 223, 183, 170, 213,
+    119, 248, 152,   2,  44, 154, 163,  70, 221, 153, 101, 155, 167,  43, 172,   9,
+    129,  22,  39, 253,  19,  98, 108, 110,  79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246,  97, 228, 251,  34, 242, 193, 238, 210, 144,  12, 191, 179, 162, 241,
+     81,  51, 145, 235, 249,  14, 239, 107,  49, 192, 214,  31, 181, 199, 106, 157,
+    184,  84, 204, 176, 115, 121,  50,  45, 127,   4, 150, 254, 138, 236, 205,  93,
+    222, 114,  67,  29,  24,  72, 243, 141, 128, 195,  78,  66, 215,  61, 156, 180,
+];
+
+fn hash(n: i64) -> usize {
+    PERMUTATION[n.rem_euclid(256) as usize] as usize
+}
+
+// smooth step with zero first and second derivatives at 0 and 1
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6f32 - 15f32) + 10f32)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+// dot product of the offset with one of twelve gradients pointing to the edges of a cube
+fn gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+// smoothly varying noise between about -1 and 1, with features about one unit apart, zero at integer points
+pub fn perlin_noise(point: Vector) -> f32 {
+    let (fx, fy, fz) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (cx, cy, cz) = (fx as i64, fy as i64, fz as i64);
+
+    // position within the unit cube
+    let (x, y, z) = (point.x - fx, point.y - fy, point.z - fz);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    // hashes of the eight corners of the cube
+    let a  = hash(cx) as i64 + cy;
+    let aa = hash(a) as i64 + cz;
+    let ab = hash(a + 1) as i64 + cz;
+    let b  = hash(cx + 1) as i64 + cy;
+    let ba = hash(b) as i64 + cz;
+    let bb = hash(b + 1) as i64 + cz;
+
+    lerp(w,
+	lerp(v,
+	    lerp(u, gradient(hash(aa), x, y, z), gradient(hash(ba), x - 1f32, y, z)),
+	    lerp(u, gradient(hash(ab), x, y - 1f32, z), gradient(hash(bb), x - 1f32, y - 1f32, z))),
+	lerp(v,
+	    lerp(u, gradient(hash(aa + 1), x, y, z - 1f32), gradient(hash(ba + 1), x - 1f32, y, z - 1f32)),
+	    lerp(u, gradient(hash(ab + 1), x, y - 1f32, z - 1f32), gradient(hash(bb + 1), x - 1f32, y - 1f32, z - 1f32))))
+}
+
+// fractional brownian motion, octaves of noise each at twice the frequency and half the amplitude of the previous one
+pub fn fbm(point: Vector, octaves: usize) -> f32 {
+    let mut sum = 0f32;
+    let mut frequency = 1f32;
+    let mut amplitude = 1f32;
+
+    for _ in 0 .. octaves {
+	sum += perlin_noise(point * frequency) * amplitude;
+	frequency *= 2f32;
+	amplitude *= 0.5f32;
+    }
+
+    sum
+}
+
+// like fbm, but with the absolute value of every octave, which gives creases where the noise crosses zero
+pub fn turbulence(point: Vector, octaves: usize) -> f32 {
+    let mut sum = 0f32;
+    let mut frequency = 1f32;
+    let mut amplitude = 1f32;
+
+    for _ in 0 .. octaves {
+	sum += perlin_noise(point * frequency).abs() * amplitude;
+	frequency *= 2f32;
+	amplitude *= 0.5f32;
+    }
+
+    sum
+}
@@ -0,0 +1,238 @@
+// loads wavefront obj files, only the geometry is read, materials and smoothing groups are ignored,
+// as are statements that are not about polygons, such as curves, which are reported once per keyword
+
+use std::fs;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::model::*;
+use crate::polygon::*;
+
+pub fn load_obj(path: &str) -> Result<RawModel, String> {
+    println!("loading: {}", path);
+
+    let text = fs::read_to_string(path).map_err(|error| format!("couldn't read the file {}: {}", path, error))?;
+
+    parse_obj(&text).map_err(|(line, message)| format!("{} line {}: {}", path, line, message))
+}
+
+// errors are returned together with their line number
+pub fn parse_obj(text: &str) -> Result<RawModel, (usize, String)> {
+    let mut raw_model = RawModel::empty();
+    let mut ignored_keywords: Vec<String> = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+	let line_number = n + 1;
+
+	let content = match line.find('#') {
+	    Some(start) => &line[.. start],
+	    None => line,
+	};
+	let mut words = content.split_whitespace();
+
+	let keyword = match words.next() {
+	    Some(keyword) => keyword,
+	    None => continue,
+	};
+
+	let parse_f32 = |word: Option<&str>, what: &str| -> Result<f32, (usize, String)> {
+	    match word {
+		Some(word) => word.parse().map_err(|_| (line_number, format!("couldn't parse {} '{}'", what, word))),
+		None => Err((line_number, format!("couldn't read {}", what))),
+	    }
+	};
+
+	match keyword {
+	    "v" => {
+		let x = parse_f32(words.next(), "x")?;
+		let y = parse_f32(words.next(), "y")?;
+		let z = parse_f32(words.next(), "z")?;
+		// followed by an optional w, which is ignored, or by a color, as some exporters write
+		let rest: Vec<&str> = words.by_ref().collect();
+		match rest.len() {
+		    0 | 1 => {},
+		    3 => raw_model.colors.push(Color {
+			r: parse_f32(Some(rest[0]), "red")?,
+			g: parse_f32(Some(rest[1]), "green")?,
+			b: parse_f32(Some(rest[2]), "blue")?,
+		    }),
+		    _ => return Err((line_number, format!("expected x y z, x y z w or x y z r g b, found {} numbers", 3 + rest.len()))),
+		}
+		raw_model.vertices.push(Vector { x, y, z });
+	    },
+	    "vn" => {
+		let x = parse_f32(words.next(), "x")?;
+		let y = parse_f32(words.next(), "y")?;
+		let z = parse_f32(words.next(), "z")?;
+		raw_model.normals.push(Vector { x, y, z });
+	    },
+	    "vt" => {
+		let u = parse_f32(words.next(), "u")?;
+		// v is optional, as is w which is ignored
+		let v = match words.next() {
+		    Some(word) => parse_f32(Some(word), "v")?,
+		    None => 0f32,
+		};
+		words.next();
+		raw_model.texture_coordinates.push((u, v));
+	    },
+	    "f" => {
+		let mut corners = Vec::new();
+		for word in words.by_ref() {
+		    corners.push(parse_corner(word, &raw_model).map_err(|message| (line_number, message))?);
+		}
+
+		if corners.len() < 3 {
+		    return Err((line_number, String::from("a face needs at least three vertices")));
+		}
+
+		let points: Vec<Vector> = corners.iter().map(|corner| raw_model.vertices[corner.vertex]).collect();
+		for (a, b, c) in triangulate_polygon(&points) {
+		    let (a, b, c) = (corners[a], corners[b], corners[c]);
+
+		    raw_model.triangle_indices.push((a.vertex, b.vertex, c.vertex));
+		    raw_model.normal_indices.push(match (a.normal, b.normal, c.normal) {
+			(Some(na), Some(nb), Some(nc)) => Some((na, nb, nc)),
+			_ => None,
+		    });
+		    raw_model.texture_coordinate_indices.push(match (a.texture_coordinate, b.texture_coordinate, c.texture_coordinate) {
+			(Some(ta), Some(tb), Some(tc)) => Some((ta, tb, tc)),
+			_ => None,
+		    });
+		}
+	    },
+	    "o" | "g" => {
+		// groups can have several names, which are kept together
+		let name: Vec<&str> = words.by_ref().collect();
+		raw_model.groups.push(Group {
+		    name: name.join(" "),
+		    first_triangle: raw_model.triangle_indices.len(),
+		});
+	    },
+	    "s" | "usemtl" | "mtllib" | "l" | "p" => {
+		// not needed for rendering the geometry
+		continue;
+	    },
+	    _ => {
+		if !ignored_keywords.iter().any(|ignored| ignored == keyword) {
+		    println!("ignoring the '{}' statements, first found on line {}", keyword, line_number);
+		    ignored_keywords.push(String::from(keyword));
+		}
+		continue;
+	    },
+	}
+
+	if let Some(word) = words.next() {
+	    return Err((line_number, format!("unexpected '{}' at the end of the line", word)));
+	}
+    }
+
+    // colors are only kept when every vertex has one
+    if raw_model.colors.len() != raw_model.vertices.len() {
+	raw_model.colors.clear();
+    }
+
+    Ok(raw_model)
+}
+
+#[derive(Copy, Clone)]
+struct Corner {
+    vertex: usize,
+    texture_coordinate: Option<usize>,
+    normal: Option<usize>,
+}
+
+// parses v, v/vt, v//vn or v/vt/vn
+fn parse_corner(word: &str, raw_model: &RawModel) -> Result<Corner, String> {
+    let mut parts = word.split('/');
+
+    let vertex = match parts.next() {
+	Some(part) => resolve_index(part, raw_model.vertices.len(), "vertex")?,
+	None => return Err(format!("couldn't read vertex in '{}'", word)),
+    };
+
+    let texture_coordinate = match parts.next() {
+	Some("") | None => None,
+	Some(part) => Some(resolve_index(part, raw_model.texture_coordinates.len(), "texture coordinate")?),
+    };
+
+    let normal = match parts.next() {
+	Some("") | None => None,
+	Some(part) => Some(resolve_index(part, raw_model.normals.len(), "normal")?),
+    };
+
+    if parts.next().is_some() {
+	return Err(format!("couldn't parse face vertex '{}'", word));
+    }
+
+    Ok(Corner {
+	vertex,
+	texture_coordinate,
+	normal,
+    })
+}
+
+// indices count from 1, negative ones count back from the last element defined so far
+fn resolve_index(part: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = part.parse().map_err(|_| format!("couldn't parse {} index '{}'", what, part))?;
+
+    let resolved = if index > 0 {
+	index - 1
+    } else {
+	count as i64 + index
+    };
+
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+	return Err(format!("{} index {} is out of range", what, index));
+    }
+
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTICES: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nvn 0 0 -1\n";
+
+    fn parse(faces: &str) -> RawModel {
+	parse_obj(&format!("{}{}", VERTICES, faces)).unwrap_or_else(|(line, message)| panic!("line {}: {}", line, message))
+    }
+
+    #[test]
+    fn corners_can_have_texture_coordinates_and_normals() {
+	let raw_model = parse("f 1 2 3\nf 1/1 2/2 3/3\nf 1//2 2//2 3//1\nf 1/3/1 2/2/1 3/1/2\n");
+
+	assert_eq!(raw_model.triangle_indices, vec![(0, 1, 2); 4]);
+	assert_eq!(raw_model.texture_coordinate_indices, vec![None, Some((0, 1, 2)), None, Some((2, 1, 0))]);
+	assert_eq!(raw_model.normal_indices, vec![None, None, Some((1, 1, 0)), Some((0, 0, 1))]);
+    }
+
+    #[test]
+    fn corners_without_a_normal_leave_the_face_without_normals() {
+	let raw_model = parse("f 1//1 2//1 3\n");
+
+	assert_eq!(raw_model.normal_indices, vec![None]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element_so_far() {
+	let raw_model = parse("f -4/-3/-2 -3/-2/-1 -2/-1/-1\nv 2 2 0\nf -3 -2 -1\n");
+
+	assert_eq!(raw_model.triangle_indices, vec![(0, 1, 2), (2, 3, 4)]);
+	assert_eq!(raw_model.texture_coordinate_indices[0], Some((0, 1, 2)));
+	assert_eq!(raw_model.normal_indices[0], Some((0, 1, 1)));
+    }
+
+    #[test]
+    fn indices_out_of_range_are_errors_on_their_line() {
+	let error = |faces: &str| parse_obj(&format!("{}{}", VERTICES, faces)).err().unwrap();
+
+	assert_eq!(error("f 0 1 2\n"), (10, String::from("vertex index 0 is out of range")));
+	assert_eq!(error("\nf 1 2 5\n"), (11, String::from("vertex index 5 is out of range")));
+	assert_eq!(error("f 1 2 -5\n"), (10, String::from("vertex index -5 is out of range")));
+	assert_eq!(error("f 1/4 2/1 3/1\n"), (10, String::from("texture coordinate index 4 is out of range")));
+	assert_eq!(error("f 1//1 2//1 3//-3\n"), (10, String::from("normal index -3 is out of range")));
+	assert_eq!(error("f 1/1/1/1 2 3\n"), (10, String::from("couldn't parse face vertex '1/1/1/1'")));
+    }
+}
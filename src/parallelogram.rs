@@ -0,0 +1,74 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::triangle::*;
+use crate::shape::*;
+
+// flat with four corners, at corner, corner + u, corner + u + v and corner + v, the front is on the side
+// from which these are seen counterclockwise, u and v do not have to be perpendicular
+#[derive(Copy, Clone, Debug)]
+pub struct Parallelogram {
+    pub corner: Vector,
+    pub u: Vector,
+    pub v: Vector,
+    pub material: usize, // index into Scene::materials
+}
+
+impl Parallelogram {
+    // split along the diagonal from the corner, both halves get the exact same vertices there, so the intersection
+    // of triangles keeps rays from slipping through in between, and texture coordinates that run from 0 to 1 along u and v
+    fn triangles(self) -> [Triangle; 2] {
+	let far_corner = self.corner + self.u + self.v;
+	let triangle = |vertices, texture_coordinates| Triangle {
+	    vertices,
+	    normals: None,
+	    texture_coordinates: Some(texture_coordinates),
+	    colors: None,
+	    material: self.material,
+	};
+
+	[
+	    triangle([self.corner, self.corner + self.u, far_corner], [(0f32, 0f32), (1f32, 0f32), (1f32, 1f32)]),
+	    triangle([self.corner, far_corner, self.corner + self.v], [(0f32, 0f32), (1f32, 1f32), (0f32, 1f32)]),
+	]
+    }
+}
+
+impl Shape for Parallelogram {
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	// a ray can only hit both halves on the diagonal, where they are the same
+	let [first, second] = self.triangles();
+	first.intersect(ray).or_else(|| second.intersect(ray))
+    }
+
+    fn occludes(&self, ray: Ray, accept: &dyn Fn(Vector) -> bool) -> bool {
+	self.triangles().iter().any(|triangle| triangle.occludes(ray, accept))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	BoundingBox::empty()
+	    .include(self.corner)
+	    .include(self.corner + self.u)
+	    .include(self.corner + self.v)
+	    .include(self.corner + self.u + self.v)
+    }
+
+    fn area(&self) -> f32 {
+	cross(self.u, self.v).norm()
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let point = self.corner + self.u * p1 + self.v * p2;
+	ShapeSample {
+	    point,
+	    normal: cross(self.u, self.v).normalised(),
+	    error: (self.corner.abs() + self.u.abs() * p1 + self.v.abs() * p2) * rounding_error(4),
+	    pdf: 1f32 / self.area(),
+	}
+    }
+}
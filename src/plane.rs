@@ -0,0 +1,69 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::shape::*;
+
+// infinite plane, its front on the side the normal points to
+// it has no finite area, so it can not be sampled and an emissive plane only lights what runs into it
+#[derive(Copy, Clone, Debug)]
+pub struct Plane {
+    pub placement: Placement, // origin on the plane, z axis along the normal
+    pub material: usize, // index into Scene::materials
+}
+
+impl Plane {
+    pub fn new(point: Vector, normal: Vector, material: usize) -> Plane {
+	Plane {
+	    placement: Placement::new(point, normal),
+	    material,
+	}
+    }
+}
+
+impl Shape for Plane {
+    // the texture coordinates are the distances along the x and y axes of the placement
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	if local_ray.direction.z == 0f32 {
+	    return None;
+	}
+
+	let depth = -local_ray.origin.z / local_ray.direction.z;
+	if depth < local_ray.t_min || depth > local_ray.t_max {
+	    return None;
+	}
+
+	let hit = local_ray.origin + local_ray.direction * depth;
+	let point = Vector { x: hit.x, y: hit.y, z: 0f32 };
+	let local = LocalHit {
+	    depth,
+	    point,
+	    normal: Vector { x: 0f32, y: 0f32, z: 1f32 },
+	    tangent: Vector { x: 1f32, y: 0f32, z: 0f32 },
+	    texture_coordinates: (point.x, point.y),
+	};
+
+	Some((depth + shift, self.placement.surface_element(local, point.abs() * rounding_error(3), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	BoundingBox {
+	    min: Vector { x: -f32::INFINITY, y: -f32::INFINITY, z: -f32::INFINITY },
+	    max: Vector { x:  f32::INFINITY, y:  f32::INFINITY, z:  f32::INFINITY },
+	}
+    }
+
+    fn area(&self) -> f32 {
+	f32::INFINITY
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    // there is no uniform distribution over an infinite plane, this is the origin with a density of zero
+    fn sample_area(&self, _p1: f32, _p2: f32) -> ShapeSample {
+	self.placement.sample_to_world(Vector { x: 0f32, y: 0f32, z: 0f32 }, Vector { x: 0f32, y: 0f32, z: 1f32 }, 0f32)
+    }
+}
@@ -0,0 +1,469 @@
+// loads polygon file format (stanford) files, in ascii as well as binary encodings
+// vertex positions, normals and colors are read, together with the faces, other elements are skipped
+
+use std::fs;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::model::*;
+use crate::polygon::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+#[derive(Clone, Debug)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List(ScalarType, ScalarType), // type of the length, type of the elements
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    property_type: PropertyType,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+pub fn load_ply(path: &str) -> Result<RawModel, String> {
+    println!("loading: {}", path);
+
+    let bytes = fs::read(path).map_err(|error| format!("couldn't read the file {}: {}", path, error))?;
+
+    parse_ply(&bytes).map_err(|message| format!("{}: {}", path, message))
+}
+
+pub fn parse_ply(bytes: &[u8]) -> Result<RawModel, String> {
+    let (format, elements, body_start) = parse_header(bytes)?;
+
+    let mut reader = match format {
+	Format::Ascii => {
+	    let text = std::str::from_utf8(&bytes[body_start ..]).map_err(|_| String::from("the ascii data is not valid text"))?;
+	    Reader::Ascii(text.split_whitespace())
+	},
+	Format::BinaryLittleEndian => Reader::Binary { bytes: &bytes[body_start ..], position: 0, big_endian: false },
+	Format::BinaryBigEndian    => Reader::Binary { bytes: &bytes[body_start ..], position: 0, big_endian: true },
+    };
+
+    let mut raw_model = RawModel::empty();
+
+    for element in &elements {
+	match element.name.as_str() {
+	    "vertex" => read_vertices(&mut reader, element, &mut raw_model)?,
+	    "face" => read_faces(&mut reader, element, &mut raw_model)?,
+	    _ => {
+		for _ in 0 .. element.count {
+		    for property in &element.properties {
+			reader.skip_property(&property.property_type)?;
+		    }
+		}
+	    },
+	}
+    }
+
+    Ok(raw_model)
+}
+
+// returns the format, the elements and where the data starts
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), String> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    let mut position = 0;
+    let mut line_number = 0;
+
+    loop {
+	let line_end = match bytes[position ..].iter().position(|&byte| byte == b'\n') {
+	    Some(offset) => position + offset,
+	    None => return Err(String::from("the header has no end_header")),
+	};
+	let line = std::str::from_utf8(&bytes[position .. line_end]).map_err(|_| format!("header line {} is not valid text", line_number + 1))?;
+	position = line_end + 1;
+	line_number += 1;
+
+	let error = |message: &str| format!("header line {}: {}", line_number, message);
+
+	let mut words = line.split_whitespace();
+	let keyword = match words.next() {
+	    Some(keyword) => keyword,
+	    None => continue,
+	};
+
+	if line_number == 1 {
+	    if keyword != "ply" {
+		return Err(String::from("not a ply file, it does not start with 'ply'"));
+	    }
+	    continue;
+	}
+
+	match keyword {
+	    "format" => {
+		format = Some(match words.next() {
+		    Some("ascii") => Format::Ascii,
+		    Some("binary_little_endian") => Format::BinaryLittleEndian,
+		    Some("binary_big_endian") => Format::BinaryBigEndian,
+		    _ => return Err(error("unknown format")),
+		});
+	    },
+	    "element" => {
+		let name = words.next().ok_or_else(|| error("element has no name"))?;
+		let count = words.next().and_then(|word| word.parse().ok()).ok_or_else(|| error("element has no count"))?;
+		elements.push(Element {
+		    name: String::from(name),
+		    count,
+		    properties: Vec::new(),
+		});
+	    },
+	    "property" => {
+		let element = elements.last_mut().ok_or_else(|| error("property before the first element"))?;
+
+		let property_type = match words.next() {
+		    Some("list") => {
+			let length_type = parse_scalar_type(words.next()).ok_or_else(|| error("unknown list length type"))?;
+			let element_type = parse_scalar_type(words.next()).ok_or_else(|| error("unknown list element type"))?;
+			PropertyType::List(length_type, element_type)
+		    },
+		    word => PropertyType::Scalar(parse_scalar_type(word).ok_or_else(|| error("unknown property type"))?),
+		};
+		let name = words.next().ok_or_else(|| error("property has no name"))?;
+
+		element.properties.push(Property {
+		    name: String::from(name),
+		    property_type,
+		});
+	    },
+	    "comment" | "obj_info" => {},
+	    "end_header" => break,
+	    _ => return Err(error(&format!("unknown keyword '{}'", keyword))),
+	}
+    }
+
+    match format {
+	Some(format) => Ok((format, elements, position)),
+	None => Err(String::from("the header has no format")),
+    }
+}
+
+fn parse_scalar_type(word: Option<&str>) -> Option<ScalarType> {
+    match word? {
+	"char"   | "int8"    => Some(ScalarType::Int8),
+	"uchar"  | "uint8"   => Some(ScalarType::UInt8),
+	"short"  | "int16"   => Some(ScalarType::Int16),
+	"ushort" | "uint16"  => Some(ScalarType::UInt16),
+	"int"    | "int32"   => Some(ScalarType::Int32),
+	"uint"   | "uint32"  => Some(ScalarType::UInt32),
+	"float"  | "float32" => Some(ScalarType::Float32),
+	"double" | "float64" => Some(ScalarType::Float64),
+	_ => None,
+    }
+}
+
+fn read_vertices(reader: &mut Reader, element: &Element, raw_model: &mut RawModel) -> Result<(), String> {
+    let index_of = |name: &str| element.properties.iter().position(|property| property.name == name);
+
+    let position = [index_of("x"), index_of("y"), index_of("z")];
+    let normal = [index_of("nx"), index_of("ny"), index_of("nz")];
+    let color = [index_of("red"), index_of("green"), index_of("blue")];
+
+    if position.iter().any(|index| index.is_none()) {
+	return Err(String::from("the vertices have no x, y and z"));
+    }
+    let has_normals = normal.iter().all(|index| index.is_some());
+    let has_colors = color.iter().all(|index| index.is_some());
+
+    // colors stored as integers are in srgb from 0 to 255, as floats they are taken to be linear from 0 to 1
+    let color_scale = match color[0].map(|index| &element.properties[index].property_type) {
+	Some(PropertyType::Scalar(ScalarType::Float32)) | Some(PropertyType::Scalar(ScalarType::Float64)) => None,
+	Some(PropertyType::Scalar(ScalarType::UInt16)) => Some(65535f32),
+	_ => Some(255f32),
+    };
+
+    let mut values = vec![0f32; element.properties.len()];
+
+    for _ in 0 .. element.count {
+	for (n, property) in element.properties.iter().enumerate() {
+	    values[n] = match property.property_type {
+		PropertyType::Scalar(scalar_type) => reader.read(scalar_type)? as f32,
+		PropertyType::List(_, _) => {
+		    reader.skip_property(&property.property_type)?;
+		    0f32
+		},
+	    };
+	}
+
+	let get = |index: Option<usize>| values[index.unwrap()];
+
+	raw_model.vertices.push(Vector { x: get(position[0]), y: get(position[1]), z: get(position[2]) });
+
+	if has_normals {
+	    raw_model.normals.push(Vector { x: get(normal[0]), y: get(normal[1]), z: get(normal[2]) });
+	}
+
+	if has_colors {
+	    raw_model.colors.push(match color_scale {
+		Some(scale) => Color {
+		    r: srgb_to_linear(get(color[0]) / scale),
+		    g: srgb_to_linear(get(color[1]) / scale),
+		    b: srgb_to_linear(get(color[2]) / scale),
+		},
+		None => Color { r: get(color[0]), g: get(color[1]), b: get(color[2]) },
+	    });
+	}
+    }
+
+    Ok(())
+}
+
+fn read_faces(reader: &mut Reader, element: &Element, raw_model: &mut RawModel) -> Result<(), String> {
+    let indices_property = element.properties.iter().position(|property| {
+	(property.name == "vertex_indices" || property.name == "vertex_index") && matches!(property.property_type, PropertyType::List(_, _))
+    });
+    let indices_property = indices_property.ok_or_else(|| String::from("the faces have no vertex_indices"))?;
+
+    // normals in a ply file belong to the vertices, so they share their indices
+    let has_normals = !raw_model.normals.is_empty();
+    let num_vertices = raw_model.vertices.len();
+
+    let mut indices = Vec::new();
+
+    for face in 0 .. element.count {
+	for (n, property) in element.properties.iter().enumerate() {
+	    match (n == indices_property, &property.property_type) {
+		(true, PropertyType::List(length_type, element_type)) => {
+		    let length = reader.read(*length_type)? as usize;
+		    indices.clear();
+		    for _ in 0 .. length {
+			let index = reader.read(*element_type)?;
+			if !index.is_finite() || index.fract() != 0f64 || index < 0f64 || index as usize >= num_vertices {
+			    return Err(format!("face {} refers to vertex {}, which does not exist", face, index));
+			}
+			indices.push(index as usize);
+		    }
+		},
+		_ => reader.skip_property(&property.property_type)?,
+	    }
+	}
+
+	if indices.len() < 3 {
+	    return Err(format!("face {} has fewer than three vertices", face));
+	}
+
+	let points: Vec<Vector> = indices.iter().map(|&index| raw_model.vertices[index]).collect();
+	for (a, b, c) in triangulate_polygon(&points) {
+	    let triangle = (indices[a], indices[b], indices[c]);
+
+	    raw_model.triangle_indices.push(triangle);
+	    raw_model.normal_indices.push(if has_normals { Some(triangle) } else { None });
+	    raw_model.texture_coordinate_indices.push(None);
+	}
+    }
+
+    Ok(())
+}
+
+enum Reader<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary {
+	bytes: &'a [u8],
+	position: usize,
+	big_endian: bool,
+    },
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, scalar_type: ScalarType) -> Result<f64, String> {
+	match self {
+	    Reader::Ascii(words) => {
+		let word = words.next().ok_or_else(|| String::from("the file ends before all data has been read"))?;
+		word.parse().map_err(|_| format!("couldn't parse '{}'", word))
+	    },
+	    Reader::Binary { bytes, position, big_endian } => {
+		let size = match scalar_type {
+		    ScalarType::Int8 | ScalarType::UInt8 => 1,
+		    ScalarType::Int16 | ScalarType::UInt16 => 2,
+		    ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+		    ScalarType::Float64 => 8,
+		};
+		if *position + size > bytes.len() {
+		    return Err(String::from("the file ends before all data has been read"));
+		}
+
+		let mut buffer = [0u8; 8];
+		buffer[.. size].copy_from_slice(&bytes[*position .. *position + size]);
+		*position += size;
+
+		// convert to little endian
+		if *big_endian {
+		    buffer[.. size].reverse();
+		}
+
+		Ok(match scalar_type {
+		    ScalarType::Int8    => buffer[0] as i8 as f64,
+		    ScalarType::UInt8   => buffer[0] as f64,
+		    ScalarType::Int16   => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+		    ScalarType::UInt16  => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+		    ScalarType::Int32   => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+		    ScalarType::UInt32  => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+		    ScalarType::Float32 => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+		    ScalarType::Float64 => f64::from_le_bytes(buffer),
+		})
+	    },
+	}
+    }
+
+    fn skip_property(&mut self, property_type: &PropertyType) -> Result<(), String> {
+	match property_type {
+	    PropertyType::Scalar(scalar_type) => {
+		self.read(*scalar_type)?;
+	    },
+	    PropertyType::List(length_type, element_type) => {
+		let length = self.read(*length_type)? as usize;
+		for _ in 0 .. length {
+		    self.read(*element_type)?;
+		}
+	    },
+	}
+
+	Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a square with a skipped list on every vertex and face, and an element that is skipped as a whole
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property list uchar int skipped
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+property list uchar float weights
+element edge 1
+property list uchar int vertices
+end_header
+";
+
+    fn header(format: &str) -> String {
+	format!("ply\nformat {} 1.0\ncomment made for testing\n{}", format, HEADER)
+    }
+
+    fn assert_square(raw_model: &RawModel) {
+	let expected = [(0f32, 0f32), (1f32, 0f32), (1f32, 1f32), (0f32, 1f32)];
+	assert_eq!(raw_model.vertices.len(), 4);
+	for (vertex, (x, y)) in raw_model.vertices.iter().zip(expected.iter()) {
+	    assert_eq!((vertex.x, vertex.y, vertex.z), (*x, *y, 2f32));
+	}
+
+	assert_eq!(raw_model.triangle_indices.len(), 2);
+	assert!(raw_model.normals.is_empty());
+	assert!(raw_model.normal_indices.iter().all(|indices| indices.is_none()));
+
+	// integer colors are srgb
+	assert_eq!(raw_model.colors.len(), 4);
+	let color = raw_model.colors[1];
+	assert_eq!((color.r, color.g, color.b), (srgb_to_linear(1f32), srgb_to_linear(128f32 / 255f32), 0f32));
+    }
+
+    // the square in binary, with every number written by the given function
+    fn binary(format: &str, write_u8: fn(u8) -> Vec<u8>, write_f32: fn(f32) -> Vec<u8>, write_u32: fn(u32) -> Vec<u8>) -> Vec<u8> {
+	let mut bytes = header(format).into_bytes();
+	for &(x, y) in &[(0f32, 0f32), (1f32, 0f32), (1f32, 1f32), (0f32, 1f32)] {
+	    for value in [x, y, 2f32] {
+		bytes.extend(write_f32(value));
+	    }
+	    bytes.extend([255u8, 128u8, 0u8]);
+	    bytes.extend(write_u8(2));
+	    bytes.extend(write_u32(7));
+	    bytes.extend(write_u32(8));
+	}
+	bytes.extend(write_u8(0));
+	bytes.extend(write_u8(4));
+	for index in 0 .. 4 {
+	    bytes.extend(write_u32(index));
+	}
+	bytes.extend(write_u8(1));
+	bytes.extend(write_f32(0.5f32));
+	bytes.extend(write_u8(2));
+	bytes.extend(write_u32(0));
+	bytes.extend(write_u32(1));
+	bytes
+    }
+
+    const ASCII_BODY: &str = "0 0 2 255 128 0 2 7 8
+1 0 2 255 128 0 0
+1 1 2 255 128 0 1 -1
+0 1 2 255 128 0 0
+0 4 0 1 2 3 1 0.5
+2 0 1
+";
+
+    #[test]
+    fn reads_ascii() {
+	let text = format!("{}{}", header("ascii"), ASCII_BODY);
+	assert_square(&parse_ply(text.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_little_endian() {
+	let bytes = binary("binary_little_endian", |v| vec![v], |v| v.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+	assert_square(&parse_ply(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_big_endian() {
+	let bytes = binary("binary_big_endian", |v| vec![v], |v| v.to_be_bytes().to_vec(), |v| v.to_be_bytes().to_vec());
+	assert_square(&parse_ply(&bytes).unwrap());
+    }
+
+    #[test]
+    fn float_colors_are_linear() {
+	let text = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nproperty float red\nproperty float green\nproperty float blue\nend_header\n0 0 0 0.5 0.25 1\n";
+	let color = parse_ply(text.as_bytes()).unwrap().colors[0];
+	assert_eq!((color.r, color.g, color.b), (0.5f32, 0.25f32, 1f32));
+    }
+
+    #[test]
+    fn indices_must_be_whole_numbers_of_existing_vertices() {
+	for index in ["4", "-1", "1.5", "nan", "inf"] {
+	    let body = ASCII_BODY.replace("0 4 0 1 2 3", &format!("0 4 0 1 2 {}", index));
+	    let text = format!("{}{}", header("ascii"), body);
+	    let error = parse_ply(text.as_bytes()).err().unwrap_or_else(|| panic!("index {} is accepted", index));
+	    assert!(error.starts_with("face 0 refers to vertex"), "{}", error);
+	}
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+	let bytes = binary("binary_little_endian", |v| vec![v], |v| v.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+	let error = parse_ply(&bytes[.. bytes.len() - 1]).err().unwrap();
+	assert_eq!(error, "the file ends before all data has been read");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::vector::*;
+use crate::frame::*;
+
+// splits a polygon, given by its corners in order, into triangles by ear clipping, which works for concave polygons
+// as well, returned as indices into points with the same winding as the polygon,
+// a polygon that crosses itself or whose corners are not roughly in a plane is split as a fan around the first corner
+pub fn triangulate_polygon(points: &[Vector]) -> Vec<(usize, usize, usize)> {
+    assert!(points.len() >= 3);
+
+    // newell's method, the normal of the plane that fits the corners best, pointing to where they are counterclockwise
+    let mut normal = Vector { x: 0f32, y: 0f32, z: 0f32 };
+    for (n, p) in points.iter().enumerate() {
+	let q = points[(n + 1) % points.len()];
+	normal.x += (p.y - q.y) * (p.z + q.z);
+	normal.y += (p.z - q.z) * (p.x + q.x);
+	normal.z += (p.x - q.x) * (p.y + q.y);
+    }
+    if points.len() == 3 || normal.norm2() == 0f32 {
+	return fan(&(0 .. points.len()).collect::<Vec<usize>>());
+    }
+
+    // in the plane the polygon is counterclockwise
+    let frame = Frame::new(normal);
+    let flat: Vec<(f32, f32)> = points.iter().map(|p| {
+	let local = frame.to_local(*p);
+	(local.x, local.y)
+    }).collect();
+
+    let mut remaining: Vec<usize> = (0 .. points.len()).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+	let count = remaining.len();
+	let ear = (0 .. count).find(|&n| {
+	    let (a, b, c) = (remaining[(n + count - 1) % count], remaining[n], remaining[(n + 1) % count]);
+	    is_ear(&flat, &remaining, a, b, c)
+	});
+
+	match ear {
+	    Some(n) => {
+		triangles.push((remaining[(n + count - 1) % count], remaining[n], remaining[(n + 1) % count]));
+		remaining.remove(n);
+	    },
+	    None => {
+		triangles.extend(fan(&remaining));
+		return triangles;
+	    },
+	}
+    }
+    triangles.push((remaining[0], remaining[1], remaining[2]));
+
+    triangles
+}
+
+fn fan(corners: &[usize]) -> Vec<(usize, usize, usize)> {
+    (1 .. corners.len() - 1).map(|n| (corners[0], corners[n], corners[n + 1])).collect()
+}
+
+// whether the triangle a, b, c can be cut off the polygon: it turns left at b, and none of the other corners
+// lie inside it or on its edges
+fn is_ear(flat: &[(f32, f32)], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    if turn(flat[a], flat[b], flat[c]) <= 0f32 {
+	return false;
+    }
+
+    remaining.iter()
+	.map(|&n| flat[n])
+	.filter(|&p| p != flat[a] && p != flat[b] && p != flat[c])
+	.all(|p| turn(flat[a], flat[b], p) < 0f32 || turn(flat[b], flat[c], p) < 0f32 || turn(flat[c], flat[a], p) < 0f32)
+}
+
+// positive when going from p to q to r turns left
+fn turn(p: (f32, f32), q: (f32, f32), r: (f32, f32)) -> f32 {
+    (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[Vector], (a, b, c): (usize, usize, usize)) -> f32 {
+	0.5f32 * cross(points[b] - points[a], points[c] - points[a]).z
+    }
+
+    #[test]
+    fn concave_polygon_is_covered_without_overlap() {
+	// an arrow pointing up, the corner at its notch is reflex, a fan around any corner next to it would fold over
+	let points: Vec<Vector> = [(0f32, 0f32), (1f32, 1f32), (2f32, 0f32), (1f32, 3f32)].iter()
+	    .map(|&(x, y)| Vector { x, y, z: 0f32 })
+	    .collect();
+
+	let triangles = triangulate_polygon(&points);
+	assert_eq!(triangles.len(), 2);
+	for triangle in &triangles {
+	    assert!(area(&points, *triangle) > 0f32);
+	}
+	let total: f32 = triangles.iter().map(|triangle| area(&points, *triangle)).sum();
+	assert!((total - 2f32).abs() < 0.0001f32);
+    }
+
+    #[test]
+    fn clockwise_polygon_keeps_its_winding() {
+	let points: Vec<Vector> = [(0f32, 0f32), (0f32, 2f32), (1f32, 1f32), (2f32, 2f32), (2f32, 0f32)].iter()
+	    .map(|&(x, y)| Vector { x, y, z: 0f32 })
+	    .collect();
+
+	let triangles = triangulate_polygon(&points);
+	assert_eq!(triangles.len(), 3);
+	for triangle in &triangles {
+	    assert!(area(&points, *triangle) < 0f32);
+	}
+	let total: f32 = triangles.iter().map(|triangle| area(&points, *triangle)).sum();
+	assert!((total + 3f32).abs() < 0.0001f32);
+    }
+}
@@ -0,0 +1,397 @@
+use std::sync::Arc;
+use std::f32::consts::PI;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::texture::*;
+use crate::surface_element::*;
+use crate::material::*;
+use crate::microfacet::*;
+use crate::bsdf::*;
+
+// a perfectly smooth surface would need a specular lobe, so the lobes are kept very slightly rough instead
+const SMOOTHEST_ALPHA: f32 = 0.001f32;
+
+// the coat is a thin layer of lacquer
+const CLEARCOAT_IOR: f32 = 1.5f32;
+
+// one material covering most surfaces, after the disney principled brdf and the gltf pbr materials: a diffuse or
+// metallic base that can also let light through, with a sheen for cloth and a clear coat on top,
+// each layer only gets the light that the ones above it did not reflect
+#[derive(Debug)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>, // diffuse color, the reflectance of metal and the tint of transmitted light
+    pub metallic: f32, // 0 for a dielectric, up to 1 for a metal
+    pub roughness: f32, // 0 for polished, up to 1 for dull
+    pub anisotropy: f32, // 0 for round highlights, up to 1 for highlights stretched along the tangent
+    pub specular: f32, // strength of the reflection of the dielectric, 0.5 for what its index of refraction gives
+    pub ior: f32, // index of refraction of the dielectric, for both reflection and transmission
+    pub sheen: Color, // extra reflection at grazing angles, as on velvet
+    pub clearcoat: f32, // 0 for no coat, up to 1
+    pub clearcoat_roughness: f32,
+    pub transmission: f32, // fraction of the dielectric that lets light through rather than reflecting it diffusely
+}
+
+// probabilities of sampling each of the lobes
+#[derive(Copy, Clone, Debug)]
+struct Lobes {
+    diffuse: f32, // together with the sheen
+    specular: f32,
+    transmission: f32,
+    clearcoat: f32,
+}
+
+impl Principled {
+    // a plain diffuse surface that the parameters can be changed from
+    pub fn new(base_color: Arc<dyn Texture>) -> Principled {
+	Principled {
+	    base_color,
+	    metallic: 0f32,
+	    roughness: 0.5f32,
+	    anisotropy: 0f32,
+	    specular: 0.5f32,
+	    ior: 1.5f32,
+	    sheen: BLACK,
+	    clearcoat: 0f32,
+	    clearcoat_roughness: 0.03f32,
+	    transmission: 0f32,
+	}
+    }
+
+    // widths of the ggx distribution along the tangent and across it
+    fn alphas(&self) -> (f32, f32) {
+	let alpha = self.roughness * self.roughness;
+	let aspect = (1f32 - 0.9f32 * self.anisotropy).sqrt();
+	((alpha / aspect).max(SMOOTHEST_ALPHA), (alpha * aspect).max(SMOOTHEST_ALPHA))
+    }
+
+    fn clearcoat_alpha(&self) -> f32 {
+	(self.clearcoat_roughness * self.clearcoat_roughness).max(SMOOTHEST_ALPHA)
+    }
+
+    // reflectance of the dielectric, with eta the index of refraction on the other side divided by that on the side of the light
+    fn dielectric_fresnel(&self, cos_theta: f32, eta: f32) -> f32 {
+	(fresnel_dielectric(cos_theta, eta) * 2f32 * self.specular).min(1f32)
+    }
+
+    fn clearcoat_fresnel(&self, cos_theta: f32) -> f32 {
+	self.clearcoat * fresnel_dielectric(cos_theta, CLEARCOAT_IOR)
+    }
+
+    // reflectance of the metal, following schlick's approximation from the base color at normal incidence
+    fn metal_fresnel(base: Color, cos_theta: f32) -> Color {
+	let weight = (1f32 - cos_theta.clamp(0f32, 1f32)).powi(5);
+	base * (1f32 - weight) + WHITE * weight
+    }
+
+    // roughly in proportion to how much light each lobe sends towards wo, which is outside
+    fn lobes(&self, base: Color, wo: Vector) -> Lobes {
+	let dielectric = 1f32 - self.metallic;
+
+	let coated = 1f32 - self.clearcoat_fresnel(wo.z);
+	let fresnel = self.dielectric_fresnel(wo.z, self.ior);
+	let lobes = Lobes {
+	    diffuse: coated * dielectric * (1f32 - self.transmission) * (1f32 - fresnel) * (base.brightness() + self.sheen.brightness()),
+	    specular: coated * (self.metallic * Principled::metal_fresnel(base, wo.z).brightness() + dielectric * fresnel),
+	    transmission: coated * dielectric * self.transmission * (1f32 - fresnel) * base.brightness(),
+	    clearcoat: self.clearcoat_fresnel(wo.z),
+	};
+
+	let total = lobes.diffuse + lobes.specular + lobes.transmission + lobes.clearcoat;
+	if total <= 0f32 {
+	    return Lobes { diffuse: 0f32, specular: 0f32, transmission: 0f32, clearcoat: 0f32 };
+	}
+	Lobes {
+	    diffuse: lobes.diffuse / total,
+	    specular: lobes.specular / total,
+	    transmission: lobes.transmission / total,
+	    clearcoat: lobes.clearcoat / total,
+	}
+    }
+}
+
+impl Bsdf for Principled {
+    fn eval(&self, surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> Color {
+	if wo.z == 0f32 || wi.z == 0f32 {
+	    return BLACK;
+	}
+	let (alpha_x, alpha_y) = self.alphas();
+
+	if wo.z < 0f32 {
+	    // inside, mirrored to the side of the normal
+	    if self.transmission <= 0f32 {
+		return BLACK;
+	    }
+	    let wo = Vector { z: -wo.z, ..wo };
+	    let wi = Vector { z: -wi.z, ..wi };
+	    let eta = 1f32 / self.ior;
+
+	    let value = if wi.z > 0f32 {
+		let microfacet_normal = (wo + wi).normalised();
+		self.dielectric_fresnel(dot(wo, microfacet_normal), eta)
+		    * specular_reflection(wo, wi, microfacet_normal, alpha_x, alpha_y)
+	    } else {
+		match refraction_normal(wo, wi, eta) {
+		    Some(microfacet_normal) => {
+			(1f32 - self.dielectric_fresnel(dot(wo, microfacet_normal), eta))
+			    * specular_transmission(wo, wi, microfacet_normal, eta, alpha_x, alpha_y)
+		    },
+		    None => 0f32,
+		}
+	    };
+	    return WHITE * value;
+	}
+
+	let base = surface_color(self.base_color.as_ref(), surface_element);
+	let dielectric = 1f32 - self.metallic;
+
+	if wi.z < 0f32 {
+	    let microfacet_normal = match refraction_normal(wo, wi, self.ior) {
+		Some(normal) => normal,
+		None => return BLACK,
+	    };
+	    let transmitted = (1f32 - self.dielectric_fresnel(dot(wo, microfacet_normal), self.ior))
+		* specular_transmission(wo, wi, microfacet_normal, self.ior, alpha_x, alpha_y);
+	    return base * (dielectric * self.transmission * (1f32 - self.clearcoat_fresnel(wo.z)) * transmitted);
+	}
+
+	let microfacet_normal = (wo + wi).normalised();
+	let cos_h = dot(wo, microfacet_normal);
+
+	// the dielectric only diffuses the light its surface lets in, in both directions
+	let diffuse_weight = dielectric * (1f32 - self.transmission);
+	let diffuse = base * (diffuse_weight * (1f32 - self.dielectric_fresnel(wo.z, self.ior)) * (1f32 - self.dielectric_fresnel(wi.z, self.ior)) / PI);
+	let sheen = self.sheen * (diffuse_weight * (1f32 - dot(wi, microfacet_normal)).max(0f32).powi(5));
+
+	let fresnel = Principled::metal_fresnel(base, cos_h) * self.metallic + WHITE * (dielectric * self.dielectric_fresnel(cos_h, self.ior));
+	let specular = fresnel * specular_reflection(wo, wi, microfacet_normal, alpha_x, alpha_y);
+
+	let clearcoat_alpha = self.clearcoat_alpha();
+	let clearcoat = self.clearcoat_fresnel(cos_h) * specular_reflection(wo, wi, microfacet_normal, clearcoat_alpha, clearcoat_alpha);
+	let coated = (1f32 - self.clearcoat_fresnel(wo.z)) * (1f32 - self.clearcoat_fresnel(wi.z));
+
+	(diffuse + sheen + specular) * coated + WHITE * clearcoat
+    }
+
+    fn sample(&self, surface_element: &SurfaceElement, wo: Vector, u: [f32; 3]) -> Option<BsdfSample> {
+	if wo.z == 0f32 {
+	    return None;
+	}
+	let (alpha_x, alpha_y) = self.alphas();
+
+	// everything is sampled as seen from the outside
+	let inside = wo.z < 0f32;
+	let wo_outside = if inside { Vector { z: -wo.z, ..wo } } else { wo };
+
+	let (wi, transmitted) = if inside {
+	    if self.transmission <= 0f32 {
+		return None;
+	    }
+	    // only the boundary of the dielectric is seen, whether it reflects is decided by the fresnel term of the
+	    // microfacet, as the boundary can let light through tilted microfacets where its average normal reflects all
+	    let eta = 1f32 / self.ior;
+	    let microfacet_normal = sample_visible_normal_anisotropic(wo_outside, alpha_x, alpha_y, u[0], u[1]);
+	    if u[2] < self.dielectric_fresnel(dot(wo_outside, microfacet_normal), eta) {
+		(reflect(wo_outside, microfacet_normal), false)
+	    } else {
+		(refract(wo_outside, microfacet_normal, eta)?, true)
+	    }
+	} else {
+	    let base = surface_color(self.base_color.as_ref(), surface_element);
+	    let lobes = self.lobes(base, wo);
+	    if u[2] < lobes.diffuse {
+		(cosine_weighed_direction(u[0], u[1]), false)
+	    } else if u[2] < lobes.diffuse + lobes.specular {
+		(reflect(wo, sample_visible_normal_anisotropic(wo, alpha_x, alpha_y, u[0], u[1])), false)
+	    } else if u[2] < lobes.diffuse + lobes.specular + lobes.transmission {
+		(refract(wo, sample_visible_normal_anisotropic(wo, alpha_x, alpha_y, u[0], u[1]), self.ior)?, true)
+	    } else {
+		let alpha = self.clearcoat_alpha();
+		(reflect(wo, sample_visible_normal(wo, alpha, u[0], u[1])), false)
+	    }
+	};
+
+	// a microfacet can send the direction to the wrong side of the surface, where pdf only counts the other lobes,
+	// which did not produce it
+	if (wi.z < 0f32) != transmitted || wi.z == 0f32 {
+	    return None;
+	}
+	let wi = if inside { Vector { z: -wi.z, ..wi } } else { wi };
+
+	// the weight is found from the sum of all lobes, as any of them could have picked the direction
+	let pdf = self.pdf(surface_element, wo, wi);
+	if pdf <= 0f32 {
+	    return None;
+	}
+
+	Some(BsdfSample {
+	    wi,
+	    weight: self.eval(surface_element, wo, wi) * (wi.z.abs() / pdf),
+	    pdf,
+	})
+    }
+
+    fn pdf(&self, surface_element: &SurfaceElement, wo: Vector, wi: Vector) -> f32 {
+	if wo.z == 0f32 || wi.z == 0f32 {
+	    return 0f32;
+	}
+	let (alpha_x, alpha_y) = self.alphas();
+
+	if wo.z < 0f32 {
+	    // inside, mirrored to the side of the normal, with the choice of the lobe depending on the microfacet
+	    if self.transmission <= 0f32 {
+		return 0f32;
+	    }
+	    let wo = Vector { z: -wo.z, ..wo };
+	    let wi = Vector { z: -wi.z, ..wi };
+	    let eta = 1f32 / self.ior;
+
+	    if wi.z > 0f32 {
+		let microfacet_normal = (wo + wi).normalised();
+		return self.dielectric_fresnel(dot(wo, microfacet_normal), eta)
+		    * reflected_direction_pdf(visible_normal_pdf_anisotropic(wo, microfacet_normal, alpha_x, alpha_y), wo, microfacet_normal);
+	    }
+	    return match refraction_normal(wo, wi, eta) {
+		Some(microfacet_normal) => {
+		    (1f32 - self.dielectric_fresnel(dot(wo, microfacet_normal), eta))
+			* refracted_direction_pdf(wo, wi, microfacet_normal, eta, alpha_x, alpha_y)
+		},
+		None => 0f32,
+	    };
+	}
+
+	let base = surface_color(self.base_color.as_ref(), surface_element);
+	let lobes = self.lobes(base, wo);
+
+	if wi.z < 0f32 {
+	    return match refraction_normal(wo, wi, self.ior) {
+		Some(microfacet_normal) => lobes.transmission * refracted_direction_pdf(wo, wi, microfacet_normal, self.ior, alpha_x, alpha_y),
+		None => 0f32,
+	    };
+	}
+
+	let microfacet_normal = (wo + wi).normalised();
+	let clearcoat_alpha = self.clearcoat_alpha();
+
+	lobes.diffuse * wi.z / PI
+	    + lobes.specular * reflected_direction_pdf(visible_normal_pdf_anisotropic(wo, microfacet_normal, alpha_x, alpha_y), wo, microfacet_normal)
+	    + lobes.clearcoat * reflected_direction_pdf(visible_normal_pdf(wo, microfacet_normal, clearcoat_alpha), wo, microfacet_normal)
+    }
+
+    fn is_two_sided(&self) -> bool {
+	self.transmission > 0f32
+    }
+}
+
+// the density of a microfacet normal changed into that of the direction it reflects wo into
+fn reflected_direction_pdf(normal_pdf: f32, wo: Vector, microfacet_normal: Vector) -> f32 {
+    normal_pdf / (4f32 * dot(wo, microfacet_normal))
+}
+
+// the density of the direction a visible microfacet normal refracts wo into, eta as for refraction_normal
+fn refracted_direction_pdf(wo: Vector, wi: Vector, microfacet_normal: Vector, eta: f32, alpha_x: f32, alpha_y: f32) -> f32 {
+    let cos_o = dot(wo, microfacet_normal);
+    let cos_i = dot(wi, microfacet_normal);
+    let denominator = cos_o + eta * cos_i;
+
+    visible_normal_pdf_anisotropic(wo, microfacet_normal, alpha_x, alpha_y) * eta * eta * cos_i.abs() / (denominator * denominator)
+}
+
+// microfacet brdf without the fresnel term, both directions above the surface
+fn specular_reflection(wo: Vector, wi: Vector, microfacet_normal: Vector, alpha_x: f32, alpha_y: f32) -> f32 {
+    ggx_distribution_anisotropic(microfacet_normal, alpha_x, alpha_y) * smith_g2_anisotropic(wo, wi, alpha_x, alpha_y) / (4f32 * wo.z * wi.z)
+}
+
+// microfacet btdf without the fresnel term, wo above and wi below the surface, eta as for refraction_normal
+fn specular_transmission(wo: Vector, wi: Vector, microfacet_normal: Vector, eta: f32, alpha_x: f32, alpha_y: f32) -> f32 {
+    let cos_o = dot(wo, microfacet_normal);
+    let cos_i = dot(wi, microfacet_normal);
+    let denominator = cos_o + eta * cos_i;
+
+    ggx_distribution_anisotropic(microfacet_normal, alpha_x, alpha_y) * smith_g2_anisotropic(wo, wi, alpha_x, alpha_y)
+	* (cos_o * cos_i).abs() / (wo.z * wi.z.abs() * denominator * denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface_element() -> SurfaceElement {
+	SurfaceElement {
+	    position: Vector { x: 0f32, y: 0f32, z: 0f32 },
+	    error: Vector { x: 0f32, y: 0f32, z: 0f32 },
+	    normal: Vector { x: 0f32, y: 0f32, z: 1f32 },
+	    geometric_normal: Vector { x: 0f32, y: 0f32, z: 1f32 },
+	    tangent: Vector { x: 1f32, y: 0f32, z: 0f32 },
+	    texture_coordinates: (0f32, 0f32),
+	    color: None,
+	    material: 0,
+	}
+    }
+
+    fn add(sum: &mut [f64; 3], color: Color) {
+	sum[0] += color.r as f64;
+	sum[1] += color.g as f64;
+	sum[2] += color.b as f64;
+    }
+
+    // the integral of eval times the cosine over the whole sphere of directions, in stratified uniform samples
+    fn reflected_by_eval(bsdf: &Principled, wo: Vector, n: usize) -> [f64; 3] {
+	let surface_element = surface_element();
+	let mut sum = [0f64; 3];
+	for i in 0 .. n {
+	    for j in 0 .. n {
+		let z = 1f32 - 2f32 * (i as f32 + 0.5f32) / n as f32;
+		let phi = 2f32 * PI * (j as f32 + 0.5f32) / n as f32;
+		let r = (1f32 - z * z).max(0f32).sqrt();
+		let wi = Vector { x: r * phi.cos(), y: r * phi.sin(), z };
+		add(&mut sum, bsdf.eval(&surface_element, wo, wi) * wi.z.abs());
+	    }
+	}
+	sum.map(|c| c * 4f64 * std::f64::consts::PI / (n * n) as f64)
+    }
+
+    // the same integral as estimated from the samples, the weights already divide by the density
+    fn reflected_by_sampling(bsdf: &Principled, wo: Vector, n: usize, lobe_strata: usize) -> [f64; 3] {
+	let surface_element = surface_element();
+	let mut sum = [0f64; 3];
+	for i in 0 .. n {
+	    for j in 0 .. n {
+		for k in 0 .. lobe_strata {
+		    // the lobe strata are shifted by the golden ratio from one direction to the next,
+		    // the same few values for every direction would round off the probabilities of the lobes
+		    let shift = ((i * n + j) as f64 * 0.618034f64).fract() as f32;
+		    let u = [
+			(i as f32 + 0.5f32) / n as f32,
+			(j as f32 + 0.5f32) / n as f32,
+			(k as f32 + shift) / lobe_strata as f32,
+		    ];
+		    if let Some(sample) = bsdf.sample(&surface_element, wo, u) {
+			add(&mut sum, sample.weight);
+		    }
+		}
+	    }
+	}
+	sum.map(|c| c / (n * n * lobe_strata) as f64)
+    }
+
+    // directions a lobe sends to the wrong side of the surface would be weighed by the density of the other lobes,
+    // and from inside the light let through tilted microfacets has to be sampled where the average normal reflects all
+    #[test]
+    fn sampling_matches_eval_for_rough_transmission() {
+	let mut bsdf = Principled::new(Arc::new(Color { r: 0.9f32, g: 0.6f32, b: 0.3f32 }));
+	bsdf.roughness = 0.9f32;
+	bsdf.transmission = 0.5f32;
+	bsdf.clearcoat = 1f32;
+	bsdf.clearcoat_roughness = 0.8f32;
+
+	for &wo_z in &[0.05f32, 0.15f32, 0.7f32, -0.4f32, -0.9f32] {
+	    let wo = Vector { x: (1f32 - wo_z * wo_z).sqrt(), y: 0f32, z: wo_z };
+	    let expected = reflected_by_eval(&bsdf, wo, 1000);
+	    let estimate = reflected_by_sampling(&bsdf, wo, 200, 20);
+	    for (e, x) in estimate.iter().zip(expected.iter()) {
+		assert!((e - x).abs() <= 0.002f64 * x, "sampling gives {:?}, eval {:?} for {:?}", estimate, expected, wo);
+	    }
+	}
+    }
+}
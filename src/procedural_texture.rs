@@ -0,0 +1,147 @@
+// textures computed from the position or texture coordinates, most of them blend between two other textures
+
+use std::sync::Arc;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::surface_element::*;
+use crate::texture::*;
+use crate::noise::*;
+
+// low where t is 0, high where t is 1
+fn blend(low: &dyn Texture, high: &dyn Texture, t: f32, surface_element: &SurfaceElement) -> Color {
+    let t = t.clamp(0f32, 1f32);
+    low.color(surface_element) * (1f32 - t) + high.color(surface_element) * t
+}
+
+// alternating cells of size 1 / scale
+#[derive(Debug)]
+pub struct CheckerTexture {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    pub mapping: Mapping,
+    pub scale: f32,
+}
+
+impl Texture for CheckerTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	let point = self.mapping.point(surface_element) * self.scale;
+	let cell = point.x.floor() as i64 + point.y.floor() as i64 + point.z.floor() as i64;
+
+	if cell.rem_euclid(2) == 0 {
+	    self.even.color(surface_element)
+	} else {
+	    self.odd.color(surface_element)
+	}
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoiseKind {
+    Perlin,     // a single octave
+    Fbm,        // octaves added up
+    Turbulence, // absolute values of the octaves added up
+}
+
+impl NoiseKind {
+    // names as used in scene files
+    pub const NAMES: [&'static str; 3] = ["perlin", "fbm", "turbulence"];
+
+    pub fn from_name(name: &str) -> Option<NoiseKind> {
+	match name {
+	    "perlin"     => Some(NoiseKind::Perlin),
+	    "fbm"        => Some(NoiseKind::Fbm),
+	    "turbulence" => Some(NoiseKind::Turbulence),
+	    _ => None,
+	}
+    }
+}
+
+#[derive(Debug)]
+pub struct NoiseTexture {
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+    pub kind: NoiseKind,
+    pub octaves: usize, // ignored by perlin
+    pub mapping: Mapping,
+    pub scale: f32, // features are about 1 / scale apart
+}
+
+impl Texture for NoiseTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	let point = self.mapping.point(surface_element) * self.scale;
+
+	// mapped from roughly [-1, 1] to [0, 1], except for turbulence which is never negative
+	let t = match self.kind {
+	    NoiseKind::Perlin => 0.5f32 * (perlin_noise(point) + 1f32),
+	    NoiseKind::Fbm => 0.5f32 * (fbm(point, self.octaves) + 1f32),
+	    NoiseKind::Turbulence => turbulence(point, self.octaves),
+	};
+
+	blend(self.low.as_ref(), self.high.as_ref(), t, surface_element)
+    }
+}
+
+// veins along the x axis, bent by turbulence
+#[derive(Debug)]
+pub struct MarbleTexture {
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+    pub octaves: usize,
+    pub distortion: f32, // how far the veins are bent
+    pub mapping: Mapping,
+    pub scale: f32,
+}
+
+impl Texture for MarbleTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	let point = self.mapping.point(surface_element) * self.scale;
+	let phase = point.x + self.distortion * turbulence(point, self.octaves);
+	let t = 0.5f32 * (1f32 + phase.sin());
+
+	blend(self.low.as_ref(), self.high.as_ref(), t, surface_element)
+    }
+}
+
+// rings around the z axis, made irregular by noise
+#[derive(Debug)]
+pub struct WoodTexture {
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+    pub octaves: usize,
+    pub distortion: f32, // how irregular the rings are
+    pub mapping: Mapping,
+    pub scale: f32, // number of rings per unit
+}
+
+impl Texture for WoodTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	let point = self.mapping.point(surface_element) * self.scale;
+	let radius = (point.x * point.x + point.y * point.y).sqrt() + self.distortion * fbm(point, self.octaves);
+
+	// sharp edge where one ring ends and the next begins
+	let t = radius - radius.floor();
+
+	blend(self.low.as_ref(), self.high.as_ref(), t, surface_element)
+    }
+}
+
+// from one texture at start to the other at end, constant beyond them
+#[derive(Debug)]
+pub struct GradientTexture {
+    pub low: Arc<dyn Texture>,
+    pub high: Arc<dyn Texture>,
+    pub start: Vector,
+    pub end: Vector,
+    pub mapping: Mapping,
+}
+
+impl Texture for GradientTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	let point = self.mapping.point(surface_element);
+	let axis = self.end - self.start;
+	let t = dot(point - self.start, axis) / axis.norm2();
+
+	blend(self.low.as_ref(), self.high.as_ref(), t, surface_element)
+    }
+}
@@ -3,5 +3,67 @@ use crate::vector::*;
 #[derive(Copy, Clone, Debug)]
 pub struct Ray {
     pub origin: Vector,
-    pub direction: Vector // must be a unit vector
+    pub direction: Vector, // must be a unit vector
+    // only hits at depths from t_min up to t_max count
+    pub t_min: f32,
+    pub t_max: f32,
+}
+
+impl Ray {
+    pub fn new(origin: Vector, direction: Vector) -> Ray {
+	Ray {
+	    origin,
+	    direction,
+	    t_min: 0f32,
+	    t_max: f32::INFINITY,
+	}
+    }
+}
+
+// bound on the relative error of a result after n floating point operations, each rounding once
+pub fn rounding_error(n: u32) -> f32 {
+    let epsilon = 0.5f32 * f32::EPSILON * n as f32;
+    epsilon / (1f32 - epsilon)
+}
+
+// moves a point on a surface, with error bounding how far each of its coordinates may be off, far enough along the
+// normal to the side the direction points to that it is certain to be on that side of the surface, rays starting
+// there can not hit the surface they leave however small or far away from the origin it is
+pub fn offset_origin(position: Vector, error: Vector, normal: Vector, direction: Vector) -> Vector {
+    let distance = dot(normal.abs(), error);
+    let offset = if dot(direction, normal) > 0f32 { normal * distance } else { normal * -distance };
+    let moved = position + offset;
+
+    // the addition rounds as well, which could undo part of the offset
+    let away = |coordinate: f32, offset: f32| {
+	if offset > 0f32 {
+	    next_float_up(coordinate)
+	} else if offset < 0f32 {
+	    next_float_down(coordinate)
+	} else {
+	    coordinate
+	}
+    };
+
+    Vector {
+	x: away(moved.x, offset.x),
+	y: away(moved.y, offset.y),
+	z: away(moved.z, offset.z),
+    }
+}
+
+// the smallest float larger than v, one step in the bit pattern, which for negative numbers runs the other way
+fn next_float_up(v: f32) -> f32 {
+    if v.is_infinite() && v > 0f32 {
+	return v;
+    }
+    // -0 becomes +0 first, so both step to the smallest positive float
+    let v = if v == -0f32 { 0f32 } else { v };
+
+    let bits = v.to_bits();
+    f32::from_bits(if v >= 0f32 { bits + 1 } else { bits - 1 })
+}
+
+fn next_float_down(v: f32) -> f32 {
+    -next_float_up(-v)
 }
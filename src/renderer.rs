@@ -0,0 +1,140 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::color::*;
+use crate::camera::*;
+use crate::scene::*;
+use crate::rendering::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub num_samples: usize, // per pixel
+    pub min_bounces: i32, // every path gets this many bounces before russian roulette can end it
+    pub sampling_method: SamplingMethod,
+    pub num_threads: usize,
+    pub tile_size: usize, // tiles are square, except at the right and bottom edges of the image
+}
+
+impl RenderSettings {
+    // as many threads as there are cores
+    pub fn default_num_threads() -> usize {
+	thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+fn make_tiles(settings: &RenderSettings) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    for y in (0 .. settings.height).step_by(settings.tile_size) {
+	for x in (0 .. settings.width).step_by(settings.tile_size) {
+	    tiles.push(Tile {
+		x,
+		y,
+		width:  settings.tile_size.min(settings.width  - x),
+		height: settings.tile_size.min(settings.height - y),
+	    });
+	}
+    }
+
+    tiles
+}
+
+// pixels of the tile, row by row
+fn render_tile(scene: &Scene, camera: Camera, settings: &RenderSettings, tile: Tile, rng: &mut StdRng) -> Vec<Color> {
+    let mut pixels = Vec::with_capacity(tile.width * tile.height);
+
+    let width = settings.width as f32;
+    let height = settings.height as f32;
+
+    for py in tile.y .. tile.y + tile.height {
+	for px in tile.x .. tile.x + tile.width {
+	    let mut accumulator = BLACK;
+	    for _ in 0 .. settings.num_samples {
+		let p1: f32 = rng.gen::<f32>();
+		let p2: f32 = rng.gen::<f32>();
+
+		let px2 = px as f32 + p1 - 0.5f32;
+		let py2 = py as f32 + p2 - 0.5f32;
+
+		let x =  2f32 * (px2 - (width  * 0.5f32)) / height;      // using height to keep aspect ratio
+		let y = -2f32 * (py2 - (height * 0.5f32)) / height;
+
+		let ray = camera.shoot_ray(x, y);
+
+		accumulator = accumulator + scene.trace_ray(ray, settings.min_bounces, settings.sampling_method, rng);
+	    }
+
+	    pixels.push(accumulator * (1f32 / settings.num_samples as f32));
+	}
+    }
+
+    pixels
+}
+
+// renders the image in tiles, which are handed out to the worker threads one at a time
+pub fn render(scene: &Scene, camera: Camera, settings: &RenderSettings) -> Rendering {
+    assert!(settings.num_threads > 0);
+    assert!(settings.tile_size > 0);
+
+    let mut rendering = Rendering::rendering(settings.width, settings.height);
+
+    let tiles = make_tiles(settings);
+    let next_tile = AtomicUsize::new(0);
+
+    let (sender, receiver) = mpsc::channel::<(Tile, Vec<Color>)>();
+
+    thread::scope(|scope| {
+	for _ in 0 .. settings.num_threads {
+	    let sender = sender.clone();
+	    let tiles = &tiles;
+	    let next_tile = &next_tile;
+
+	    scope.spawn(move || {
+		loop {
+		    let tile_index = next_tile.fetch_add(1, Ordering::Relaxed);
+		    if tile_index >= tiles.len() {
+			break;
+		    }
+
+		    // seeded per tile, so the result does not depend on the number of threads
+		    let mut rng = StdRng::seed_from_u64(tile_index as u64);
+
+		    let pixels = render_tile(scene, camera, settings, tiles[tile_index], &mut rng);
+		    sender.send((tiles[tile_index], pixels)).unwrap();
+		}
+	    });
+	}
+
+	// otherwise the loop below would wait for this sender forever
+	drop(sender);
+
+	// only this thread writes to the rendering
+	let mut num_finished = 0;
+	for (tile, pixels) in receiver {
+	    for ty in 0 .. tile.height {
+		for tx in 0 .. tile.width {
+		    *rendering.get_mut_pixel(tile.x + tx, tile.y + ty) = pixels[tx + ty * tile.width];
+		}
+	    }
+
+	    num_finished += 1;
+	    println!("{} out of {} tiles...", num_finished, tiles.len());
+	}
+    });
+
+    rendering
+}
@@ -9,10 +9,11 @@ pub struct Rendering {
 }
 
 impl Rendering {
+    #[allow(clippy::self_named_constructors)]
     pub fn rendering(width: usize, height: usize) -> Rendering {
 	let mut rendering = Rendering {
-	    width: width,
-	    height: height,
+	    width,
+	    height,
 	    pixels: Vec::new(),
 	};
 
@@ -48,11 +49,10 @@ impl Rendering {
 	}
     }
     
-    pub fn save(self) {
+    pub fn save(self, path: &str) -> image::ImageResult<()> {
 	assert!(self.pixels.len() == self.width * self.height);
 
-	let mut quantised_pixels: Vec<u8> = Vec::new();
-	quantised_pixels.resize(self.width * self.height * 3, 0u8);
+	let mut quantised_pixels: Vec<u8> = vec![0u8; self.width * self.height * 3];
 
 	for py in 0 .. self.height {
 	    for px in 0 .. self.width {
@@ -66,6 +66,6 @@ impl Rendering {
 	
 	let img = ImageBuffer::<image::Rgb<u8>, Vec<u8>>::from_vec(self.width as u32, self.height as u32, quantised_pixels).unwrap();
 
-	img.save("test.png").unwrap();
+	img.save(path)
     }
 }
@@ -1,290 +1,374 @@
-use crate::triangle::*;
 use crate::surface_element::*;
 use crate::ray::*;
 use crate::vector::*;
 use crate::color::*;
-use crate::sphere::*;
+use crate::shape::*;
+use crate::bvh::*;
+use crate::bounding_box::*;
+use crate::distribution::*;
+use crate::instance::*;
+use crate::material::*;
+use crate::bsdf::*;
+use crate::frame::*;
 
 use std::f32::consts::PI;
+use std::sync::Arc;
 
-enum SamplingMethod {
-    Uniform,
-    NaiveImportanceSampling, // cosine weighed, not aware of the position of the light source
-    AwareImportanceSampling1, // exploits knowledge of the position of the light source
-    AwareImportanceSampling2, // exploits knowledge of the position of the light source
+use rand::{Rng, RngCore};
+
+// shadow rays stop this fraction of their length short of their target, which covers the rounding in finding
+// that length, independent of the size of the scene
+const SHADOW_EPSILON: f32 = 0.0001f32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplingMethod {
+    Uniform, // directions picked uniformly, light sources are only found by running into them
+    NaiveImportanceSampling, // directions picked by the bsdf, light sources are only found by running into them
+    MultipleImportanceSampling, // light sources sampled directly as well, combined with the bsdf through the power heuristic
+}
+
+impl SamplingMethod {
+    // names as used in scene files and on the command line
+    pub const NAMES: [&'static str; 3] = ["uniform", "naive", "mis"];
+
+    pub fn from_name(name: &str) -> Option<SamplingMethod> {
+	match name {
+	    "uniform" => Some(SamplingMethod::Uniform),
+	    "naive"   => Some(SamplingMethod::NaiveImportanceSampling),
+	    "mis"     => Some(SamplingMethod::MultipleImportanceSampling),
+	    _ => None,
+	}
+    }
 }
 
-//const SAMPLING_METHOD: SamplingMethod = SamplingMethod::Uniform;
-//const SAMPLING_METHOD: SamplingMethod = SamplingMethod::NaiveImportanceSampling;
-const SAMPLING_METHOD: SamplingMethod = SamplingMethod::AwareImportanceSampling1;
+// a point on a light source as seen from a surface
+struct LightSample {
+    point: Vector,
+    normal: Vector,
+    error: Vector, // bound on the rounding error of the point
+    direction: Vector,
+    radiance: Color,
+    pdf: f32, // with respect to solid angle as seen from the surface
+}
 
 #[derive(Clone, Debug)]
 pub struct Scene {
-    pub triangles: Vec<Triangle>,
-    pub sphere: Sphere,
+    pub materials: Vec<Material>, // referred to by index from the shapes
+    pub shapes: Vec<Arc<dyn Shape>>,
+    pub instances: Vec<Instance>,
+    // derived from the shapes and instances, must be updated with prepare after changing them
+    pub bounded_shapes: Vec<usize>, // indices into shapes, in the order of the primitives of the bvh
+    pub unbounded_shapes: Vec<usize>, // indices into shapes, those without finite bounds, which are tested one by one
+    pub bvh: Bvh,
+    pub instance_bvh: Bvh,
+    pub emitters: Vec<Arc<dyn Shape>>, // the emissive shapes, with copies of the emissive triangles of instances
+    pub light_distribution: Distribution, // by power, over the emitters
 }
 
 impl Scene {
-    fn scan_triangles(&self, ray: Ray) -> Option<(f32,SurfaceElement)> {
-	let mut best_hit = None;
-	let mut best_depth = 99999999f32;
-
-	for triangle in &self.triangles {
-	    match triangle.intersect(ray) {
-		None => {},
-		Some((depth, surface_element)) => {
-		    if depth < best_depth {
-			best_depth = depth;
-			best_hit = Some((depth,surface_element));
-		    }
-		},
+    // builds the bvhs and the distribution for picking light sources
+    pub fn prepare(&mut self) {
+	let (bounded_shapes, unbounded_shapes) = (0 .. self.shapes.len()).partition(|&index| self.shapes[index].bounds().is_finite());
+	self.bounded_shapes = bounded_shapes;
+	self.unbounded_shapes = unbounded_shapes;
+
+	let bounds: Vec<BoundingBox> = self.bounded_shapes.iter().map(|&index| self.shapes[index].bounds()).collect();
+	self.bvh = Bvh::build(&bounds);
+
+	let instance_bounds: Vec<BoundingBox> = self.instances.iter().map(|instance| instance.bounds()).collect();
+	self.instance_bvh = Bvh::build(&instance_bounds);
+
+	let instance_triangles = self.instances.iter()
+	    .flat_map(|instance| instance.world_triangles())
+	    .filter(|triangle| self.materials[triangle.material].is_emissive())
+	    .map(|triangle| Arc::new(triangle) as Arc<dyn Shape>);
+	// shapes of infinite area can not be sampled, their light is only found by running into them
+	self.emitters = self.shapes.iter()
+	    .filter(|shape| self.materials[shape.material()].is_emissive() && shape.area().is_finite())
+	    .cloned()
+	    .chain(instance_triangles)
+	    .collect();
+
+	let powers: Vec<f32> = self.emitters.iter().map(|shape| self.power(shape.as_ref())).collect();
+	self.light_distribution = Distribution::new(&powers);
+    }
+
+    // closest hit with the shapes, both those placed directly in the scene and the triangles of instances,
+    // returned with the shape that was hit, which is None for instances
+    fn closest_hit(&self, ray: Ray) -> Option<(f32, SurfaceElement, Option<&dyn Shape>)> {
+	assert!(self.bvh.num_primitives() + self.unbounded_shapes.len() == self.shapes.len(), "bvh is out of date, call prepare after changing the shapes");
+	assert!(self.instance_bvh.num_primitives() == self.instances.len(), "bvh is out of date, call prepare after changing the instances");
+
+	let accept = |surface_element: &SurfaceElement, ray: Ray| self.can_hit(ray, surface_element.geometric_normal, surface_element.material);
+
+	let intersect = |index: usize, ray: Ray| {
+	    let shape = self.shapes[index].as_ref();
+	    shape.intersect(ray)
+		.filter(|(_, surface_element)| accept(surface_element, ray))
+		.map(|(depth, surface_element)| (depth, (surface_element, shape)))
+	};
+
+	let mut shape_hit = self.bvh.closest_hit(ray, |index| intersect(self.bounded_shapes[index], ray));
+	for &index in &self.unbounded_shapes {
+	    let unbounded_ray = Ray { t_max: shape_hit.as_ref().map_or(ray.t_max, |(depth, _)| *depth), ..ray };
+	    if let Some(hit) = intersect(index, unbounded_ray) {
+		shape_hit = Some(hit);
 	    }
 	}
 
-	best_hit
+	// instances behind the closest shape do not have to be searched
+	let instance_ray = Ray { t_max: shape_hit.as_ref().map_or(ray.t_max, |(depth, _)| *depth), ..ray };
+	let instance_hit = self.instance_bvh.closest_hit(instance_ray, |index| self.instances[index].intersect(instance_ray, accept));
+
+	match (shape_hit, instance_hit) {
+	    (_, Some((depth, surface_element))) => Some((depth, surface_element, None)),
+	    (Some((depth, (surface_element, shape))), None) => Some((depth, surface_element, Some(shape))),
+	    (None, None) => None,
+	}
     }
 
-    // finds the light leaving the surface element in the specified direction
-    // convention for direction_out to be the direction INTO the surface
-    // convention for direction_in to be OUT OF the surface
-    // i.e. both in the direction of ray tracing, and opposite to the direction of the light
-    fn light_out(&self, surface_element: SurfaceElement, _direction_out: Vector, recurse: i32) -> Color {
-	assert!(surface_element.normal.is_normal());
-	assert!(_direction_out.is_normal());
-	assert!(recurse >= 0);
-	
-	if recurse == 0 {
-	    return BLACK;
+    // whether anything blocks the segment from origin to target, which should both have been moved off the surfaces
+    // they are on, stops at the first blocker found instead of looking for the closest one
+    pub fn occluded(&self, origin: Vector, target: Vector) -> bool {
+	assert!(self.bvh.num_primitives() + self.unbounded_shapes.len() == self.shapes.len(), "bvh is out of date, call prepare after changing the shapes");
+	assert!(self.instance_bvh.num_primitives() == self.instances.len(), "bvh is out of date, call prepare after changing the instances");
+
+	let to_target = target - origin;
+	let distance = to_target.norm();
+	if distance == 0f32 {
+	    return false;
 	}
 
-	match SAMPLING_METHOD {
-	    SamplingMethod::Uniform => {
-		let p: f32 = rand::random();
-		let theta: f32 = p.acos();
-		let omega: f32 = 2f32 * PI * rand::random::<f32>();
-
-		let (v1, v2) = surface_element.normal.make_orthogonal_frame();
-		let direction_in = surface_element.normal * theta.cos() + (v1 * omega.cos() + v2 * omega.sin()) * theta.sin();
-		assert!(direction_in.is_normal());
-		
-		let ray = Ray {
-		    origin: surface_element.position,
-		    direction: direction_in,
-		};
+	let ray = Ray {
+	    origin,
+	    direction: to_target * (1f32 / distance),
+	    t_min: 0f32,
+	    t_max: distance * (1f32 - SHADOW_EPSILON),
+	};
+
+	let occludes = |index: usize| {
+	    let shape = &self.shapes[index];
+	    shape.occludes(ray, &|normal| self.can_hit(ray, normal, shape.material()))
+	};
+
+	self.unbounded_shapes.iter().any(|&index| occludes(index))
+	    || self.bvh.any_hit(ray, |index| occludes(self.bounded_shapes[index]))
+	    || self.instance_bvh.any_hit(ray, |index| {
+		self.instances[index].occludes(ray, |normal, material, ray| self.can_hit(ray, normal, material))
+	    })
+    }
 
-		let flux_in = self.trace_ray(ray, recurse - 1);
+    // the back of a surface is only hit when the material is two-sided
+    fn can_hit(&self, ray: Ray, geometric_normal: Vector, material: usize) -> bool {
+	dot(ray.direction, geometric_normal) < 0f32 || self.materials[material].is_two_sided()
+    }
 
-		flux_in * surface_element.material.diffuse_color * (theta.cos() * 2f32)
-	    },
-	    SamplingMethod::NaiveImportanceSampling => {
-		let p: f32 = rand::random::<f32>();
-		let theta: f32 = (1f32 - 2f32 * p).acos() / 2f32;
-
-		let omega: f32 = 2f32 * PI * rand::random::<f32>();
-
-		let (v1, v2) = surface_element.normal.make_orthogonal_frame();
-		let direction_in = surface_element.normal * theta.cos() + (v1 * omega.cos() + v2 * omega.sin()) * theta.sin();
-		assert!(direction_in.is_normal());
-		
-		let ray = Ray {
-		    origin: surface_element.position,
-		    direction: direction_in,
+    // follows a path from the camera, adding up the light found at each of its vertices weighed by the throughput,
+    // the product of the bsdf weights so far, which is the fraction of that light that makes it to the camera,
+    // after min_bounces bounces the path is ended at random, more likely as the throughput drops (russian roulette),
+    // and the paths that survive are weighed up to make up for those that did not
+    pub fn trace_ray(&self, ray: Ray, min_bounces: i32, method: SamplingMethod, rng: &mut dyn RngCore) -> Color {
+	assert!(min_bounces >= 0);
+
+	let mut ray = ray;
+	let mut radiance = BLACK;
+	let mut throughput = WHITE;
+	let mut num_bounces = 0;
+
+	// density of the direction the ray was sent in, when the light it finds has been sampled directly as well
+	// and the two estimates are to be combined, None when it counts in full: for camera rays, after specular
+	// surfaces, which can not be sampled directly, and when light sources are not sampled at all
+	let mut direction_pdf = None;
+
+	while let Some((depth, surface_element, shape)) = self.closest_hit(ray) {
+	    assert!(surface_element.normal.is_normal());
+
+	    throughput = throughput * self.absorption(ray, depth, &surface_element);
+
+	    // only the front of a surface emits light
+	    let material = &self.materials[surface_element.material];
+	    let cos_theta_light = -dot(ray.direction, surface_element.geometric_normal);
+	    if material.is_emissive() && cos_theta_light > 0f32 {
+		let weight = match direction_pdf {
+		    Some(pdf) => power_heuristic(pdf, self.emitter_pdf(ray.origin, &surface_element, shape)),
+		    None => 1f32,
 		};
+		radiance = radiance + throughput * material.emission * weight;
+	    }
 
-		let flux_in = self.trace_ray(ray, recurse - 1);
+	    let bsdf = material.bsdf.as_ref();
+	    let frame = Frame::with_tangent(surface_element.normal, surface_element.tangent);
+	    let wo = frame.to_local(-ray.direction);
 
-		flux_in * surface_element.material.diffuse_color
-	    },
-	    SamplingMethod::AwareImportanceSampling1 => {
-		let direction_sphere = (self.sphere.position - surface_element.position).normalised();
-		let theta_sphere_cos = dot(surface_element.normal, direction_sphere);
-		let theta_sphere = theta_sphere_cos.acos();
-		
-		let distance_sphere = (self.sphere.position - surface_element.position).norm();
-		
-		// angle between the lines from the surface element to the center of the sphere and to the edge of the sphere
-		let disc_angle = (self.sphere.radius / distance_sphere).atan();
-		let disc_area = 2f32 * PI * (1f32 - disc_angle.cos());
-		
-		let theta_max = theta_sphere + disc_angle;
-		
-		let brightness_ambient = 0.001f32; // TEMP <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-		let brightness_disc = (self.sphere.color.r + self.sphere.color.g + self.sphere.color.b) / 3f32;
-		
-		let alpha = if theta_max > 0.5f32 * PI {
-		    //part of the sphere is below the horizon
-		    0f32
-		} else {
-		    let beta = (brightness_disc / brightness_ambient - 1f32) * disc_area / PI;
-		    assert!(beta > 0f32);
-		    beta * theta_sphere_cos / (1f32 + beta * theta_sphere_cos)
-		}.max(0f32).min(0.75f32); // artificial cap at 0.75 is hacky <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-
-		let ray_direction = if rand::random::<f32>() < alpha {
-		    // sample towards light source
-		    let cos_theta = 1f32 - rand::random::<f32>() * (1f32 - disc_angle.cos());
-		    let theta = cos_theta.acos();
-		    let phi = 2f32 * PI * rand::random::<f32>();
-		    let (v1, v2) = direction_sphere.make_orthogonal_frame();
-
-		    direction_sphere * cos_theta + (v1 * phi.cos() + v2 * phi.sin()) * theta.sin()
-		} else {
-		    // sample at random, cosine weighed
-		    let theta: f32 = rand::random::<f32>().acos();
-		    let omega: f32 = 2f32 * PI * rand::random::<f32>();
-
-		    let (v1, v2) = surface_element.normal.make_orthogonal_frame();
-		    surface_element.normal * theta.cos() + (v1 * omega.cos() + v2 * omega.sin()) * theta.sin()
-		};
-		assert!(ray_direction.is_normal());
-		
-		let ray = Ray {
-		    origin: surface_element.position,
-		    direction: ray_direction,
-		};
+	    let specular = bsdf.is_specular();
+	    let sample_lights = method == SamplingMethod::MultipleImportanceSampling && !specular;
+	    if sample_lights {
+		radiance = radiance + throughput * self.direct_light(&surface_element, frame, wo, bsdf, rng);
+	    }
 
-		// notice that this code is the same as will be used by ray tracing,
-		// so most importantly will never give a false negative
-		let towards_disc = self.sphere.intersect(ray).is_some();
+	    let (wi, weight, pdf) = match self.sample_direction(&surface_element, wo, bsdf, method, rng) {
+		Some(sample) => sample,
+		None => break,
+	    };
+	    if !consistent_sides(&surface_element, frame, wo, wi) {
+		break;
+	    }
 
-		let cos_theta_in = dot(ray_direction, surface_element.normal);
-		
-		let denominator = (1f32 - alpha) * cos_theta_in + if towards_disc {
-		     alpha * PI / disc_area
-		} else {
-		    0f32
-		};
+	    throughput = throughput * weight;
+	    direction_pdf = if sample_lights { Some(pdf) } else { None };
+	    ray = offset_ray(&surface_element, frame.to_world(wi).normalised());
+	    num_bounces += 1;
 
-		let flux_in = self.trace_ray(ray, recurse - 1);
+	    if num_bounces > min_bounces {
+		let survival = throughput.max_channel().min(0.95f32);
+		if rng.gen::<f32>() >= survival {
+		    break;
+		}
+		throughput = throughput * (1f32 / survival);
+	    } else if throughput.max_channel() <= 0f32 {
+		break;
+	    }
+	}
 
-		let flux_out = flux_in * surface_element.material.diffuse_color * (cos_theta_in / denominator);
+	radiance
+    }
 
-		flux_out
-	    },
-	    SamplingMethod::AwareImportanceSampling2 => {
-		// calculate the disc related to what part of the light source is above the horizon
-		// in case the light souce is entirely below the horizon, defaults to a zero-width cone allong the normal
-		let (direction_disc, disc_angle) = {
-		    let direction_sphere = (self.sphere.position - surface_element.position).normalised();
-		    let cos_theta_sphere = dot(surface_element.normal, direction_sphere);
-		    let theta_sphere = cos_theta_sphere.acos();
-		    
-		    let distance_sphere = (self.sphere.position - surface_element.position).norm();
-
-		    // angle between the lines from the surface element to the center of the sphere and to the edge of the sphere
-		    let apparent_angle = (self.sphere.radius / distance_sphere).atan();
-
-		    let theta_min = theta_sphere - apparent_angle;
-
-		    if theta_min > PI * 0.5f32 {
-			// sphere is not visible at all
-			(surface_element.normal, 0f32)
-		    } else {
-			let theta_max = (theta_sphere + apparent_angle).min(PI * 0.5f32);
-			let theta_disc = (theta_max + theta_min) * 0.5f32;
-			let disc_angle   = (theta_max - theta_min) * 0.5f32;
-
-			let direction_sphere_in_plane = (direction_sphere - surface_element.normal * cos_theta_sphere).normalised();
-
-			let direction_disc = surface_element.normal * theta_disc.cos() + direction_sphere_in_plane * theta_disc.sin();
-
-			(direction_disc, disc_angle)
-		    }
-		};
-		
-		let brightness_disc = (self.sphere.color.r + self.sphere.color.g + self.sphere.color.b) / 3f32;
-		let area_disc = 2f32 * PI * (1f32 - disc_angle.cos());
-		
-		let brightness_ambient = 0.01f32; //TEMP <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-		
-		//make sure alpha behaves appropriately when area_disc goes to zero
-		let mut alpha =
-		    (brightness_disc - brightness_ambient) / (
-			brightness_disc - brightness_ambient * (1f32 - PI / (area_disc * dot(direction_disc, surface_element.normal)))
-		    );
-		alpha = alpha.max(0f32);
-		alpha = alpha.min(0.25f32);     // TEMP taking .min(0.2f32) is a hack <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-
-		alpha = 0.25f32; // EXTREME HACK <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<
-		
-		assert!(alpha <= 1f32);
-		if disc_angle == 0f32 {
-		    assert!(alpha == 0f32);
+    // picks the direction in the local frame to follow the light back in, returned with the weight to multiply
+    // the light arriving from there by, the bsdf times the cosine divided by the pdf, and the pdf itself
+    fn sample_direction(&self, surface_element: &SurfaceElement, wo: Vector, bsdf: &dyn Bsdf, method: SamplingMethod, rng: &mut dyn RngCore) -> Option<(Vector, Color, f32)> {
+	let u = [rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()];
+
+	// specular surfaces can only be sampled by the bsdf
+	if method == SamplingMethod::Uniform && !bsdf.is_specular() {
+	    // over the hemisphere above the surface, or the whole sphere when light can pass through
+	    let cos_theta = u[0];
+	    let sin_theta = (1f32 - cos_theta * cos_theta).max(0f32).sqrt();
+	    let phi = 2f32 * PI * u[1];
+
+	    let mut wi = Vector { x: sin_theta * phi.cos(), y: sin_theta * phi.sin(), z: cos_theta };
+	    let mut pdf = 1f32 / (2f32 * PI);
+	    if bsdf.is_two_sided() {
+		if u[2] < 0.5f32 {
+		    wi.z = -wi.z;
 		}
+		pdf *= 0.5f32;
+	    }
 
-		let direction_in = if rand::random::<f32>() < alpha {
-		    // sample on the disc pointing towards the light source
-		    assert!(disc_angle > 0f32);
+	    return Some((wi, bsdf.eval(surface_element, wo, wi) * (wi.z.abs() / pdf), pdf));
+	}
+
+	bsdf.sample(surface_element, wo, u)
+	    .filter(|sample| sample.pdf > 0f32)
+	    .map(|sample| (sample.wi, sample.weight, sample.pdf))
+    }
 
-		    let p1 = rand::random::<f32>();
-		    let cos_theta = 1f32 - p1 * (1f32 - disc_angle.cos());
-		    let theta = cos_theta.acos();
+    // estimates the light arriving directly from the light sources and scattered in the direction wo, from a point
+    // on one of them, weighed against finding the same light by sampling the bsdf
+    fn direct_light(&self, surface_element: &SurfaceElement, frame: Frame, wo: Vector, bsdf: &dyn Bsdf, rng: &mut dyn RngCore) -> Color {
+	let light_sample = match self.sample_light(surface_element.position, rng) {
+	    Some(light_sample) => light_sample,
+	    None => return BLACK,
+	};
 
-		    let p2 = rand::random::<f32>();
-		    let omega = 2f32 * PI * p2;
+	let wi = frame.to_local(light_sample.direction);
+	if !consistent_sides(surface_element, frame, wo, wi) {
+	    return BLACK;
+	}
 
-		    let (v1, v2) = direction_disc.make_orthogonal_frame();
-		    direction_disc * cos_theta + (v1 * omega.cos() + v2 * omega.sin()) * theta.sin()
-		} else {
-		    // sample cosine-weighed
-		    let p: f32 = rand::random::<f32>();
-		    let theta: f32 = (1f32 - 2f32 * p).acos() / 2f32;
+	let bsdf_value = bsdf.eval(surface_element, wo, wi);
+	if bsdf_value.max_channel() <= 0f32 {
+	    return BLACK;
+	}
 
-		    let omega: f32 = 2f32 * PI * rand::random::<f32>();
+	// both ends of the segment are moved off their surfaces, so only what lies in between can block it
+	let origin = offset_origin(surface_element.position, surface_element.error, surface_element.geometric_normal, light_sample.direction);
+	let target = offset_origin(light_sample.point, light_sample.error, light_sample.normal, -light_sample.direction);
+	if self.occluded(origin, target) {
+	    return BLACK;
+	}
 
-		    let (v1, v2) = surface_element.normal.make_orthogonal_frame();
-		    let direction_in = surface_element.normal * theta.cos() + (v1 * omega.cos() + v2 * omega.sin()) * theta.sin();
+	let weight = power_heuristic(light_sample.pdf, bsdf.pdf(surface_element, wo, wi));
+	light_sample.radiance * bsdf_value * (wi.z.abs() * weight / light_sample.pdf)
+    }
 
-		    direction_in
-		};
-		assert!(direction_in.is_normal());
-		
-		let towards_disc = dot(direction_in, direction_disc) > disc_angle.cos();
-
-		let cos_theta_in = dot(direction_in, surface_element.normal);
-		let denominator = if towards_disc {
-		    1f32 + alpha * (PI / (area_disc * cos_theta_in) - 1f32)
-		} else {
-		    1f32 - alpha
-		};
-		
-		let ray = Ray {
-		    origin: surface_element.position,
-		    direction: direction_in,
-		};
+    // picks a point on one of the light sources, with a probability proportional to their power,
+    // None when there are none or the point can not be seen from the position
+    fn sample_light(&self, position: Vector, rng: &mut dyn RngCore) -> Option<LightSample> {
+	if !self.light_distribution.can_sample() {
+	    return None;
+	}
 
-		let flux_in = self.trace_ray(ray, recurse - 1);
-		let flux_out = flux_in * surface_element.material.diffuse_color * (cos_theta_in / denominator);
+	let n = self.light_distribution.sample(rng.gen::<f32>());
+	let shape = &self.emitters[n];
+	let sample = shape.sample(position, rng.gen::<f32>(), rng.gen::<f32>())?;
+	let direction = (sample.point - position).normalised();
 
-		flux_out
-	    },
+	// the back of a surface does not emit
+	if dot(direction, sample.normal) >= 0f32 {
+	    return None;
 	}
+
+	Some(LightSample {
+	    point: sample.point,
+	    normal: sample.normal,
+	    error: sample.error,
+	    direction,
+	    radiance: self.materials[shape.material()].emission,
+	    pdf: self.light_distribution.probability(n) * sample.pdf,
+	})
     }
-    
-    pub fn trace_ray(&self, ray: Ray, recurse: i32) -> Color {
-	assert!(recurse >= 0);
-	
-	let triangle_hit = self.scan_triangles(ray);
-	let sphere_hit = self.sphere.intersect(ray);
-	
-	
-	match (triangle_hit, sphere_hit) {
-	    (None, None) => { BLACK },
-	    (Some((_,surface_element)), None) => {
-		assert!(surface_element.normal.is_normal());
-		self.light_out(surface_element, ray.direction, recurse)
-	    },
-	    (None, Some(_)) => {
-		self.sphere.color
-	    },
-	    (Some((triangle_depth, surface_element)), Some(sphere_depth)) => {
-		if triangle_depth < sphere_depth {
-		    assert!(surface_element.normal.is_normal());
-		self.light_out(surface_element, ray.direction, recurse)
-		} else {
-		    self.sphere.color
-		}
+
+    // light emitted by the front of an emissive shape, with every point emitting in all directions
+    fn power(&self, shape: &dyn Shape) -> f32 {
+	PI * self.materials[shape.material()].emission.brightness() * shape.area()
+    }
+
+    // density with respect to solid angle of sample_light picking the point on an emitter that a ray from origin hit,
+    // without the shape this is a triangle of an instance, which like all triangles is sampled by area
+    fn emitter_pdf(&self, origin: Vector, surface_element: &SurfaceElement, shape: Option<&dyn Shape>) -> f32 {
+	// the probability of picking the shape, power over total power, is its area times this
+	let density = PI * self.materials[surface_element.material].emission.brightness() / self.light_distribution.total();
+
+	match shape {
+	    Some(shape) if !shape.area().is_finite() => 0f32, // never sampled
+	    Some(shape) => density * shape.area() * shape.pdf(origin, surface_element.position, surface_element.geometric_normal),
+	    None => {
+		let to_point = surface_element.position - origin;
+		let distance = to_point.norm();
+		area_to_solid_angle(density, distance, dot(to_point, surface_element.geometric_normal).abs() / distance)
 	    },
 	}
     }
+
+    // hitting a surface from behind means the ray travelled through it, and part of the light was absorbed on the way,
+    // this assumes nothing else is inside
+    fn absorption(&self, ray: Ray, depth: f32, surface_element: &SurfaceElement) -> Color {
+	if dot(ray.direction, surface_element.geometric_normal) > 0f32 {
+	    self.materials[surface_element.material].bsdf.transmittance(depth)
+	} else {
+	    WHITE
+	}
+    }
+}
+
+// the shading normal can put a direction on the other side of the surface than the geometric normal does,
+// light is only exchanged when both agree on whether it is reflected or transmitted
+fn consistent_sides(surface_element: &SurfaceElement, frame: Frame, wo: Vector, wi: Vector) -> bool {
+    let reflected = dot(frame.to_world(wo), surface_element.geometric_normal) * dot(frame.to_world(wi), surface_element.geometric_normal) > 0f32;
+    wi.z != 0f32 && reflected == same_hemisphere(wo, wi)
+}
+
+// moved off the surface, to the side the ray leaves on, so it does not hit the surface it starts on
+fn offset_ray(surface_element: &SurfaceElement, direction: Vector) -> Ray {
+    let origin = offset_origin(surface_element.position, surface_element.error, surface_element.geometric_normal, direction);
+    Ray::new(origin, direction)
+}
+
+// weight for combining two ways of sampling, for a sample taken with density pdf which the other one would have taken with other_pdf
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0f32 {
+	return 0f32;
+    }
+    a / (a + b)
 }
@@ -0,0 +1,957 @@
+// loads scenes from a text file, one statement per line:
+//
+//   # comments start with a hash
+//   resolution 240 135
+//   samples 15
+//   bounces 5
+//   sampling mis
+//   exposure 25
+//   gamma 0.6
+//   camera position 0 -0.9 0.5 forward 0 1 0 right 1 0 0 up 0 0 1
+//   material red diffuse 0.9 0 0
+//   material lamp diffuse 0 0 0 emission 4 4 4
+//   texture photo image textures/photo.png wrap mirror
+//   texture tiles checker even 0.9 0.9 0.9 odd photo scale 8
+//   texture stone marble low 0.2 0.2 0.3 high 0.9 0.9 0.9 octaves 6 distortion 5 scale 4
+//   material floor diffuse tiles
+//   material chrome mirror 0.95 0.95 0.95
+//   material bottle glass 1.5 roughness 0.1 absorption 0.5 0.1 0.5
+//   material ring metal gold roughness 0.3
+//   material pan metal 0.2 0.92 1.1  3.9 2.45 2.14
+//   material paint principled base 0.6 0.05 0.05 roughness 0.4 clearcoat 1
+//   material velvet principled base 0.3 0 0.1 roughness 1 sheen 0.5 0.5 0.5
+//   material brushed principled base 0.9 0.9 0.9 metallic 1 roughness 0.3 anisotropy 0.8
+//   material helmet gltf models/helmet.gltf visor
+//   triangle red 1 1 0  -1 1 0  1 -1 0
+//   sphere position 0.5 0.5 1.5 radius 0.1 color 1 1 1
+//   sphere position 0 0.4 0.25 radius 0.25 material chrome
+//   plane point 0 0 0 normal 0 0 1 material floor
+//   disc center 0 0 1.99 normal 0 0 -1 radius 0.3 material lamp
+//   quad corner -1 1 0 u 2 0 0 v 0 0 2 material red
+//   box min -0.5 -0.5 0 max -0.2 -0.2 0.6 material red
+//   box center 0.4 0.2 0.3 size 0.3 0.3 0.6 rotate 0 0 20 material chrome
+//   cylinder base 0 0 0 top 0 0 1 radius 0.1 material red
+//   cone base 0 0 0 apex 0 0 1 radius 0.3 material red
+//   torus center 0 0 0.5 axis 0 0 1 major 0.3 minor 0.1 material ring
+//   mesh bunny material red
+//   mesh models/teapot.obj material red group lid
+//   mesh models/dragon.ply material red
+//   mesh bunny material red up y scale 5 5 5 rotate 0 0 90 translate 0 0.2 -0.2
+//   mesh models/teapot.obj material red normals smooth
+//   object tree models/tree.obj material bark up y
+//   instance tree translate 2 0 0
+//   instance tree material red rotate 0 0 45 translate 3 1 0
+//
+// a statement is a keyword followed by its properties, a property is a name followed by its values,
+// every path bounces the given number of times before it may be ended at random,
+// triangle vertices are given counterclockwise as seen from the front,
+// a sphere has either a material or the color of the light it emits without reflecting any,
+// the other shapes take a material, which makes them area lights when it is emissive, except for planes,
+// which are infinite and only light what runs into them, shapes are only seen from the front unless light passes
+// through their material, the front of planes and discs faces along the normal, that of a quad is where corner, corner + u
+// and corner + u + v are counterclockwise, the others are seen from the outside,
+// a box is rotated around its center, cylinders and cones are closed at their ends, the torus is the tube of the minor
+// radius around the circle of the major radius, which must be larger, around the axis,
+// a material reflects diffusely (the default), as a perfect mirror or as metal, given by name (one of gold, copper,
+// aluminium or silver) or by its complex index of refraction eta + i k as eta for red, green and blue followed by k,
+// or it is glass with the given index of refraction and an absorption coefficient per unit of distance,
+// metal and glass have a roughness from 0 for polished to 1 for dull,
+// a principled material combines these, with the properties base <texture>, metallic, roughness, specular (0.5 for
+// the plain reflection of the dielectric), ior, sheen <color>, clearcoat, clearcoat_roughness, transmission and
+// anisotropy (along the direction in which the texture coordinate u increases), all from 0 to 1 except ior,
+// gltf <path> <name> loads a principled material from a gltf file, which the properties after it can change,
+// wherever a texture is expected a color can be given instead, the textures are
+//   image <path> [wrap repeat|mirror|clamp]
+//   checker [even <texture>] [odd <texture>] [scale <cells per unit>] [mapping uv|position]
+//   noise [low <texture>] [high <texture>] [type perlin|fbm|turbulence] [octaves <n>] [scale <s>] [mapping uv|position]
+//   marble [low <texture>] [high <texture>] [octaves <n>] [distortion <d>] [scale <s>] [mapping uv|position]
+//   wood [low <texture>] [high <texture>] [octaves <n>] [distortion <d>] [scale <rings per unit>] [mapping uv|position]
+//   gradient [low <texture>] [high <texture>] [start <x y z>] [end <x y z>] [mapping uv|position]
+// checker and gradient use the texture coordinates by default, the others the position,
+// meshes are scaled, then rotated (in degrees around x, y and z in that order) and then translated,
+// mesh normals are flat, file (from the model file where it has them, the default) or smooth (computed where the file has none),
+// an object is a mesh that is loaded once and only appears in the scene through its instances,
+// which share its triangles but each have their own placement and optionally replace its material
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::triangle::*;
+use crate::sphere::*;
+use crate::plane::*;
+use crate::disc::*;
+use crate::parallelogram::*;
+use crate::cuboid::*;
+use crate::cylinder::*;
+use crate::cone::*;
+use crate::torus::*;
+use crate::frame::*;
+use crate::shape::*;
+use crate::material::*;
+use crate::camera::*;
+use crate::scene::*;
+use crate::model::*;
+use crate::bvh::*;
+use crate::distribution::*;
+use crate::renderer::*;
+use crate::instance::*;
+use crate::texture::*;
+use crate::image_texture::*;
+use crate::procedural_texture::*;
+use crate::conductor::*;
+use crate::dielectric::*;
+use crate::bsdf::*;
+use crate::diffuse::*;
+use crate::mirror::*;
+use crate::principled::*;
+use crate::gltf::*;
+
+pub struct SceneDescription {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub settings: RenderSettings,
+    pub exposure: f32, // the rendering is scaled by this before applying gamma
+    pub gamma: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneFileError {
+    pub line: Option<usize>, // counting from 1, None for errors about the file as a whole
+    pub message: String,
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self.line {
+	    Some(line) => write!(f, "line {}: {}", line, self.message),
+	    None => write!(f, "{}", self.message),
+	}
+    }
+}
+
+pub fn load_scene_file(path: &str) -> Result<SceneDescription, SceneFileError> {
+    let text = fs::read_to_string(path).map_err(|error| SceneFileError {
+	line: None,
+	message: format!("couldn't read the file {}: {}", path, error),
+    })?;
+
+    parse_scene(&text)
+}
+
+// the words of one line, with the line number attached to any error
+struct Statement<'a> {
+    line: usize,
+    words: SplitWhitespace<'a>,
+}
+
+impl<'a> Statement<'a> {
+    fn error(&self, message: String) -> SceneFileError {
+	SceneFileError {
+	    line: Some(self.line),
+	    message,
+	}
+    }
+
+    fn next_word(&mut self, what: &str) -> Result<&'a str, SceneFileError> {
+	match self.words.next() {
+	    Some(word) => Ok(word),
+	    None => Err(self.error(format!("expected {}", what))),
+	}
+    }
+
+    fn next_f32(&mut self, what: &str) -> Result<f32, SceneFileError> {
+	let word = self.next_word(what)?;
+	word.parse().map_err(|_| self.error(format!("expected {}, found '{}'", what, word)))
+    }
+
+    fn next_usize(&mut self, what: &str) -> Result<usize, SceneFileError> {
+	let word = self.next_word(what)?;
+	word.parse().map_err(|_| self.error(format!("expected {}, found '{}'", what, word)))
+    }
+
+    fn next_vector(&mut self, what: &str) -> Result<Vector, SceneFileError> {
+	Ok(Vector {
+	    x: self.next_f32(&format!("x of {}", what))?,
+	    y: self.next_f32(&format!("y of {}", what))?,
+	    z: self.next_f32(&format!("z of {}", what))?,
+	})
+    }
+
+    fn next_color(&mut self, what: &str) -> Result<Color, SceneFileError> {
+	Ok(Color {
+	    r: self.next_f32(&format!("red of {}", what))?,
+	    g: self.next_f32(&format!("green of {}", what))?,
+	    b: self.next_f32(&format!("blue of {}", what))?,
+	})
+    }
+
+    // nan and infinity are parsed as well, and are rejected here
+    fn next_positive(&mut self, what: &str) -> Result<f32, SceneFileError> {
+	let value = self.next_f32(what)?;
+	if value > 0f32 && value.is_finite() {
+	    Ok(value)
+	} else {
+	    Err(self.error(format!("the {} must be a positive number", what)))
+	}
+    }
+
+    // for light, which can not be negative
+    fn next_emission(&mut self, what: &str) -> Result<Color, SceneFileError> {
+	let color = self.next_color(what)?;
+	if [color.r, color.g, color.b].iter().any(|c| *c < 0f32 || c.is_nan()) {
+	    return Err(self.error(format!("the {} can not be negative", what)));
+	}
+	Ok(color)
+    }
+
+    fn end(&mut self) -> Result<(), SceneFileError> {
+	match self.words.next() {
+	    None => Ok(()),
+	    Some(word) => Err(self.error(format!("unexpected '{}' at the end of the line", word))),
+	}
+    }
+
+    fn unknown_property(&self, keyword: &str, property: &str) -> SceneFileError {
+	self.error(format!("unknown property '{}' for {}", property, keyword))
+    }
+}
+
+// the kinds of material a scene file can describe, collected before roughness and absorption are applied
+enum Reflection {
+    Diffuse(Arc<dyn Texture>),
+    Mirror(Arc<dyn Texture>),
+    Metal(Conductor),
+    Glass(Dielectric),
+    Principled(Principled),
+}
+
+// state while parsing, everything that can be set by a statement
+struct Parser {
+    scene: Scene,
+    camera: Option<Camera>,
+    settings: RenderSettings,
+    exposure: f32,
+    gamma: f32,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, usize>, // indices into Scene::materials
+    objects: HashMap<String, Arc<Mesh>>,
+}
+
+pub fn parse_scene(text: &str) -> Result<SceneDescription, SceneFileError> {
+    let mut parser = Parser {
+	scene: Scene {
+	    materials: Vec::new(),
+	    shapes: Vec::new(),
+	    instances: Vec::new(),
+	    bounded_shapes: Vec::new(),
+	    unbounded_shapes: Vec::new(),
+	    bvh: Bvh::empty(),
+	    instance_bvh: Bvh::empty(),
+	    emitters: Vec::new(),
+	    light_distribution: Distribution::empty(),
+	},
+	camera: None,
+	settings: RenderSettings {
+	    width: 240,
+	    height: 135,
+	    num_samples: 15,
+	    min_bounces: 5,
+	    sampling_method: SamplingMethod::MultipleImportanceSampling,
+	    num_threads: RenderSettings::default_num_threads(),
+	    tile_size: 16,
+	},
+	exposure: 1f32,
+	gamma: 1f32,
+	textures: HashMap::new(),
+	materials: HashMap::new(),
+	objects: HashMap::new(),
+    };
+
+    for (n, line) in text.lines().enumerate() {
+	// everything after a hash is a comment
+	let content = match line.find('#') {
+	    Some(start) => &line[.. start],
+	    None => line,
+	};
+
+	let mut statement = Statement {
+	    line: n + 1,
+	    words: content.split_whitespace(),
+	};
+
+	let keyword = match statement.words.next() {
+	    Some(keyword) => keyword,
+	    None => continue, // empty line
+	};
+
+	parser.parse_statement(keyword, &mut statement)?;
+	statement.end()?;
+    }
+
+    let camera = match parser.camera {
+	Some(camera) => camera,
+	None => return Err(SceneFileError {
+	    line: None,
+	    message: String::from("the scene has no camera"),
+	}),
+    };
+
+    parser.scene.prepare();
+
+    Ok(SceneDescription {
+	scene: parser.scene,
+	camera,
+	settings: parser.settings,
+	exposure: parser.exposure,
+	gamma: parser.gamma,
+    })
+}
+
+impl Parser {
+    fn parse_statement(&mut self, keyword: &str, statement: &mut Statement) -> Result<(), SceneFileError> {
+	match keyword {
+	    "resolution" => {
+		self.settings.width = statement.next_usize("width")?;
+		self.settings.height = statement.next_usize("height")?;
+		if self.settings.width == 0 || self.settings.height == 0 {
+		    return Err(statement.error(String::from("the resolution must be at least 1 by 1")));
+		}
+	    },
+	    "samples" => {
+		self.settings.num_samples = statement.next_usize("number of samples per pixel")?;
+		if self.settings.num_samples == 0 {
+		    return Err(statement.error(String::from("there must be at least one sample per pixel")));
+		}
+	    },
+	    "bounces" => {
+		let bounces = statement.next_usize("number of bounces")?;
+		self.settings.min_bounces = match i32::try_from(bounces) {
+		    Ok(bounces) => bounces,
+		    Err(_) => return Err(statement.error(format!("the number of bounces can be at most {}", i32::MAX))),
+		};
+	    },
+	    "sampling" => {
+		let name = statement.next_word("sampling method")?;
+		self.settings.sampling_method = match SamplingMethod::from_name(name) {
+		    Some(method) => method,
+		    None => return Err(statement.error(format!("unknown sampling method '{}', expected one of {}", name, SamplingMethod::NAMES.join(", ")))),
+		};
+	    },
+	    "exposure" => {
+		self.exposure = statement.next_positive("exposure")?;
+	    },
+	    "gamma" => {
+		self.gamma = statement.next_positive("gamma")?;
+	    },
+	    "camera" => {
+		self.camera = Some(Parser::parse_camera(statement)?);
+	    },
+	    "texture" => {
+		let name = statement.next_word("texture name")?;
+		let texture = self.parse_texture(statement)?;
+		self.textures.insert(String::from(name), texture);
+	    },
+	    "material" => {
+		let name = statement.next_word("material name")?;
+		let material = self.parse_material(statement)?;
+		self.materials.insert(String::from(name), self.scene.materials.len());
+		self.scene.materials.push(material);
+	    },
+	    "triangle" => {
+		let material = self.material(statement)?;
+
+		let p0 = statement.next_vector("first vertex")?;
+		let p1 = statement.next_vector("second vertex")?;
+		let p2 = statement.next_vector("third vertex")?;
+
+		let triangle = Triangle {
+		    vertices: [p0, p1, p2],
+		    normals: None,
+		    texture_coordinates: None,
+		    colors: None,
+		    material,
+		};
+		if triangle.area() <= 0f32 {
+		    return Err(statement.error(String::from("the triangle has no area")));
+		}
+
+		self.scene.shapes.push(Arc::new(triangle));
+	    },
+	    "sphere" => {
+		let sphere = self.parse_sphere(statement)?;
+		self.scene.shapes.push(Arc::new(sphere));
+	    },
+	    "plane" | "disc" | "quad" | "box" | "cylinder" | "cone" | "torus" => {
+		let shape = self.parse_primitive(keyword, statement)?;
+		self.scene.shapes.push(shape);
+	    },
+	    "mesh" => {
+		let model = self.parse_model(statement)?;
+		self.scene.shapes.extend(model.triangles.into_iter().map(|triangle| Arc::new(triangle) as Arc<dyn Shape>));
+	    },
+	    "object" => {
+		let name = statement.next_word("object name")?;
+		let model = self.parse_model(statement)?;
+		self.objects.insert(String::from(name), Arc::new(Mesh::new(model.triangles)));
+	    },
+	    "instance" => {
+		let name = statement.next_word("object name")?;
+		let mesh = match self.objects.get(name) {
+		    Some(mesh) => mesh.clone(),
+		    None => return Err(statement.error(format!("unknown object '{}'", name))),
+		};
+
+		let mut material = None;
+		let mut transform = ModelTransform::identity();
+		while let Some(property) = statement.words.next() {
+		    if property == "material" {
+			material = Some(self.material(statement)?);
+		    } else if !Parser::parse_transform_property(statement, property, &mut transform)? {
+			return Err(statement.unknown_property("instance", property));
+		    }
+		}
+
+		self.scene.instances.push(Instance {
+		    mesh,
+		    transform: transform.transform(),
+		    material,
+		});
+	    },
+	    _ => {
+		return Err(statement.error(format!("unknown keyword '{}'", keyword)));
+	    },
+	}
+
+	Ok(())
+    }
+
+    // reads the name of a material that has been defined before, returns its index in the scene
+    fn material(&self, statement: &mut Statement) -> Result<usize, SceneFileError> {
+	let name = statement.next_word("material name")?;
+	match self.materials.get(name) {
+	    Some(material) => Ok(*material),
+	    None => Err(statement.error(format!("unknown material '{}'", name))),
+	}
+    }
+
+    // a model file with its material and placement, as used by mesh and object
+    fn parse_model(&self, statement: &mut Statement) -> Result<Model, SceneFileError> {
+	let name = statement.next_word("model")?;
+
+	let mut material = None;
+	let mut group = None;
+	let mut vertex_normals = VertexNormals::FromFile;
+	let mut transform = ModelTransform::identity();
+	while let Some(property) = statement.words.next() {
+	    match property {
+		"material" => material = Some(self.material(statement)?),
+		"group"    => group = Some(statement.next_word("group name")?),
+		"normals" => {
+		    vertex_normals = match statement.next_word("normals")? {
+			"flat"   => VertexNormals::Flat,
+			"file"   => VertexNormals::FromFile,
+			"smooth" => VertexNormals::Computed,
+			normals => return Err(statement.error(format!("the normals must be flat, file or smooth, found '{}'", normals))),
+		    };
+		},
+		_ => {
+		    if !Parser::parse_transform_property(statement, property, &mut transform)? {
+			return Err(statement.unknown_property("mesh", property));
+		    }
+		},
+	    }
+	}
+
+	let material = match material {
+	    Some(material) => material,
+	    None => return Err(statement.error(String::from("the mesh has no material"))),
+	};
+
+	let mut raw_model = RawModel::load(name).map_err(|message| statement.error(message))?;
+	if let Some(group) = group {
+	    raw_model = raw_model.select_group(group).map_err(|message| statement.error(message))?;
+	}
+	Ok(Model::from_raw(raw_model, material, transform.transform(), vertex_normals))
+    }
+
+    // reads the values of up, scale, rotate and translate, returns false for any other property
+    fn parse_transform_property(statement: &mut Statement, property: &str, transform: &mut ModelTransform) -> Result<bool, SceneFileError> {
+	match property {
+	    "up" => {
+		transform.up_axis = match statement.next_word("up axis")? {
+		    "y" => UpAxis::Y,
+		    "z" => UpAxis::Z,
+		    axis => return Err(statement.error(format!("the up axis must be y or z, found '{}'", axis))),
+		};
+	    },
+	    "scale" => {
+		transform.scale = statement.next_vector("scale")?;
+		if transform.scale.x == 0f32 || transform.scale.y == 0f32 || transform.scale.z == 0f32 {
+		    return Err(statement.error(String::from("the scale can not be zero")));
+		}
+	    },
+	    "rotate"    => transform.rotation = statement.next_vector("rotation")?,
+	    "translate" => transform.translation = statement.next_vector("translation")?,
+	    _ => return Ok(false),
+	}
+
+	Ok(true)
+    }
+
+    fn parse_camera(statement: &mut Statement) -> Result<Camera, SceneFileError> {
+	let mut position = None;
+	let mut forward = None;
+	let mut right = None;
+	let mut up = None;
+
+	while let Some(property) = statement.words.next() {
+	    match property {
+		"position" => position = Some(statement.next_vector("camera position")?),
+		"forward"  => forward  = Some(statement.next_vector("camera forward")?),
+		"right"    => right    = Some(statement.next_vector("camera right")?),
+		"up"       => up       = Some(statement.next_vector("camera up")?),
+		_ => return Err(statement.unknown_property("camera", property)),
+	    }
+	}
+
+	match (position, forward, right, up) {
+	    (Some(position), Some(forward), Some(right), Some(up)) => Ok(Camera {
+		position,
+		forward,
+		right,
+		up,
+	    }),
+	    _ => Err(statement.error(String::from("the camera needs a position, forward, right and up"))),
+	}
+    }
+
+    fn parse_material(&self, statement: &mut Statement) -> Result<Material, SceneFileError> {
+	let mut reflection = Reflection::Diffuse(Arc::new(BLACK));
+	let mut emission = BLACK;
+	let mut roughness = None;
+	let mut absorption = None;
+
+	while let Some(property) = statement.words.next() {
+	    match property {
+		"diffuse"   => reflection = Reflection::Diffuse(self.texture(statement, "diffuse color")?),
+		"mirror"    => reflection = Reflection::Mirror(self.texture(statement, "mirror color")?),
+		"metal"     => reflection = Reflection::Metal(Parser::parse_conductor(statement)?),
+		"glass" => {
+		    let ior = statement.next_positive("index of refraction")?;
+		    reflection = Reflection::Glass(Dielectric {
+			ior,
+			roughness: 0f32,
+			absorption: BLACK,
+		    });
+		},
+		"principled" => reflection = Reflection::Principled(Principled::new(Arc::new(Color { r: 0.8f32, g: 0.8f32, b: 0.8f32 }))),
+		"gltf" => {
+		    let path = statement.next_word("gltf path")?;
+		    let name = statement.next_word("gltf material name")?;
+		    let (principled, gltf_emission) = load_gltf_material(path, name).map_err(|message| statement.error(message))?;
+		    reflection = Reflection::Principled(principled);
+		    emission = gltf_emission;
+		},
+		"base" | "metallic" | "specular" | "ior" | "sheen" | "clearcoat" | "clearcoat_roughness" | "transmission" | "anisotropy" => {
+		    match &mut reflection {
+			Reflection::Principled(principled) => self.parse_principled_property(statement, property, principled)?,
+			_ => return Err(statement.error(format!("only principled materials have {}, it must come after principled or gltf", property))),
+		    }
+		},
+		"absorption" => absorption = Some(statement.next_color("absorption")?),
+		"roughness" => roughness = Some(statement.next_f32("roughness")?),
+		"emission"  => emission = statement.next_emission("emission")?,
+		_ => return Err(statement.unknown_property("material", property)),
+	    }
+	}
+
+	if let Some(roughness) = roughness {
+	    if !(0f32 ..= 1f32).contains(&roughness) {
+		return Err(statement.error(String::from("the roughness must be between 0 and 1")));
+	    }
+	    match &mut reflection {
+		Reflection::Metal(conductor) => conductor.roughness = roughness,
+		Reflection::Glass(dielectric) => dielectric.roughness = roughness,
+		Reflection::Principled(principled) => principled.roughness = roughness,
+		_ => return Err(statement.error(String::from("only metal, glass and principled materials have a roughness"))),
+	    }
+	}
+
+	if let Some(absorption) = absorption {
+	    match &mut reflection {
+		Reflection::Glass(dielectric) => dielectric.absorption = absorption,
+		_ => return Err(statement.error(String::from("only glass has an absorption"))),
+	    }
+	}
+
+	let bsdf: Arc<dyn Bsdf> = match reflection {
+	    Reflection::Diffuse(texture) => Arc::new(Diffuse { texture }),
+	    Reflection::Mirror(texture) => Arc::new(Mirror { texture }),
+	    Reflection::Metal(conductor) => Arc::new(conductor),
+	    Reflection::Glass(dielectric) => Arc::new(dielectric),
+	    Reflection::Principled(principled) => Arc::new(principled),
+	};
+
+	Ok(Material {
+	    bsdf,
+	    emission,
+	})
+    }
+
+    fn parse_principled_property(&self, statement: &mut Statement, property: &str, principled: &mut Principled) -> Result<(), SceneFileError> {
+	if property == "base" {
+	    principled.base_color = self.texture(statement, "base color")?;
+	    return Ok(());
+	}
+	if property == "sheen" {
+	    principled.sheen = statement.next_color("sheen")?;
+	    return Ok(());
+	}
+	if property == "ior" {
+	    principled.ior = statement.next_positive("index of refraction")?;
+	    return Ok(());
+	}
+
+	let value = statement.next_f32(property)?;
+	if !(0f32 ..= 1f32).contains(&value) {
+	    return Err(statement.error(format!("the {} must be between 0 and 1", property)));
+	}
+	match property {
+	    "metallic"            => principled.metallic = value,
+	    "specular"            => principled.specular = value,
+	    "clearcoat"           => principled.clearcoat = value,
+	    "clearcoat_roughness" => principled.clearcoat_roughness = value,
+	    "transmission"        => principled.transmission = value,
+	    "anisotropy"          => principled.anisotropy = value,
+	    _ => return Err(statement.unknown_property("material", property)),
+	}
+	Ok(())
+    }
+
+    // either the name of a metal, or the real and imaginary parts of its index of refraction for red, green and blue
+    fn parse_conductor(statement: &mut Statement) -> Result<Conductor, SceneFileError> {
+	let starts_with_number = statement.words.clone().next().is_some_and(|word| word.parse::<f32>().is_ok());
+	if starts_with_number {
+	    return Ok(Conductor {
+		eta: statement.next_color("index of refraction")?,
+		k: statement.next_color("extinction coefficient")?,
+		roughness: 0f32,
+	    });
+	}
+
+	let name = statement.next_word("metal")?;
+	match Conductor::preset(name) {
+	    Some(conductor) => Ok(conductor),
+	    None => Err(statement.error(format!("unknown metal '{}', expected one of {} or an index of refraction", name, Conductor::PRESETS.join(", ")))),
+	}
+    }
+
+    // reads either a color or the name of a texture that has been defined before
+    fn texture(&self, statement: &mut Statement, what: &str) -> Result<Arc<dyn Texture>, SceneFileError> {
+	let starts_with_number = statement.words.clone().next().is_some_and(|word| word.parse::<f32>().is_ok());
+	if starts_with_number {
+	    return Ok(Arc::new(statement.next_color(what)?));
+	}
+
+	let name = statement.next_word(what)?;
+	match self.textures.get(name) {
+	    Some(texture) => Ok(texture.clone()),
+	    None => Err(statement.error(format!("unknown texture '{}'", name))),
+	}
+    }
+
+    fn parse_texture(&self, statement: &mut Statement) -> Result<Arc<dyn Texture>, SceneFileError> {
+	let kind = statement.next_word("texture type")?;
+
+	let allowed_properties: &[&str] = match kind {
+	    "image"    => &["wrap"],
+	    "checker"  => &["even", "odd", "mapping", "scale"],
+	    "noise"    => &["low", "high", "type", "octaves", "mapping", "scale"],
+	    "marble"   => &["low", "high", "octaves", "distortion", "mapping", "scale"],
+	    "wood"     => &["low", "high", "octaves", "distortion", "mapping", "scale"],
+	    "gradient" => &["low", "high", "start", "end", "mapping"],
+	    _ => return Err(statement.error(format!("unknown texture type '{}', expected one of image, checker, noise, marble, wood or gradient", kind))),
+	};
+
+	let path = if kind == "image" { Some(statement.next_word("image path")?) } else { None };
+
+	let white: Arc<dyn Texture> = Arc::new(Color { r: 1f32, g: 1f32, b: 1f32 });
+	let black: Arc<dyn Texture> = Arc::new(BLACK);
+
+	let mut wrap_mode = WrapMode::Repeat;
+	let (mut even, mut odd) = (white.clone(), black.clone());
+	let (mut low, mut high) = (black, white);
+	let mut noise_kind = NoiseKind::Perlin;
+	let mut octaves = 4;
+	let mut distortion = 1f32;
+	let mut mapping = if kind == "checker" || kind == "gradient" { Mapping::Uv } else { Mapping::Position };
+	let mut scale = 1f32;
+	let mut start = Vector { x: 0f32, y: 0f32, z: 0f32 };
+	let mut end = Vector { x: 1f32, y: 0f32, z: 0f32 };
+
+	while let Some(property) = statement.words.next() {
+	    if !allowed_properties.contains(&property) {
+		return Err(statement.unknown_property(&format!("{} texture", kind), property));
+	    }
+
+	    match property {
+		"wrap" => {
+		    let name = statement.next_word("wrap mode")?;
+		    wrap_mode = match WrapMode::from_name(name) {
+			Some(wrap_mode) => wrap_mode,
+			None => return Err(statement.error(format!("unknown wrap mode '{}', expected one of {}", name, WrapMode::NAMES.join(", ")))),
+		    };
+		},
+		"even" => even = self.texture(statement, "even texture")?,
+		"odd"  => odd  = self.texture(statement, "odd texture")?,
+		"low"  => low  = self.texture(statement, "low texture")?,
+		"high" => high = self.texture(statement, "high texture")?,
+		"type" => {
+		    let name = statement.next_word("noise type")?;
+		    noise_kind = match NoiseKind::from_name(name) {
+			Some(noise_kind) => noise_kind,
+			None => return Err(statement.error(format!("unknown noise type '{}', expected one of {}", name, NoiseKind::NAMES.join(", ")))),
+		    };
+		},
+		"octaves"    => octaves = statement.next_usize("number of octaves")?,
+		"distortion" => distortion = statement.next_f32("distortion")?,
+		"mapping" => {
+		    let name = statement.next_word("mapping")?;
+		    mapping = match Mapping::from_name(name) {
+			Some(mapping) => mapping,
+			None => return Err(statement.error(format!("unknown mapping '{}', expected one of {}", name, Mapping::NAMES.join(", ")))),
+		    };
+		},
+		"scale" => scale = statement.next_f32("scale")?,
+		"start" => start = statement.next_vector("gradient start")?,
+		"end"   => end   = statement.next_vector("gradient end")?,
+		_ => unreachable!(),
+	    }
+	}
+
+	Ok(match kind {
+	    "image" => {
+		let texture = ImageTexture::load(path.unwrap(), wrap_mode).map_err(|message| statement.error(message))?;
+		Arc::new(texture)
+	    },
+	    "checker" => Arc::new(CheckerTexture { even, odd, mapping, scale }),
+	    "noise" => Arc::new(NoiseTexture { low, high, kind: noise_kind, octaves, mapping, scale }),
+	    "marble" => Arc::new(MarbleTexture { low, high, octaves, distortion, mapping, scale }),
+	    "wood" => Arc::new(WoodTexture { low, high, octaves, distortion, mapping, scale }),
+	    "gradient" => {
+		if (end - start).norm2() == 0f32 {
+		    return Err(statement.error(String::from("the gradient needs different start and end points")));
+		}
+		Arc::new(GradientTexture { low, high, start, end, mapping })
+	    },
+	    _ => unreachable!(),
+	})
+    }
+
+    // a color instead of a material adds a material that only emits light
+    fn parse_sphere(&mut self, statement: &mut Statement) -> Result<Sphere, SceneFileError> {
+	let mut position = None;
+	let mut radius = None;
+	let mut material = None;
+
+	while let Some(property) = statement.words.next() {
+	    match property {
+		"position" => position = Some(statement.next_vector("sphere position")?),
+		"radius"   => radius   = Some(statement.next_positive("sphere radius")?),
+		"material" => material = Some(self.material(statement)?),
+		"color" => {
+		    let emission = statement.next_emission("sphere color")?;
+		    material = Some(self.scene.materials.len());
+		    self.scene.materials.push(Material {
+			bsdf: Arc::new(Diffuse { texture: Arc::new(BLACK) }),
+			emission,
+		    });
+		},
+		_ => return Err(statement.unknown_property("sphere", property)),
+	    }
+	}
+
+	match (position, radius, material) {
+	    (Some(position), Some(radius), Some(material)) => Ok(Sphere {
+		position,
+		radius,
+		material,
+	    }),
+	    _ => Err(statement.error(String::from("the sphere needs a position, radius and a material or color"))),
+	}
+    }
+
+    // the shapes other than triangles and spheres, which all take a material and vectors and numbers
+    // as properties, which ones depends on the shape
+    fn parse_primitive(&self, keyword: &str, statement: &mut Statement) -> Result<Arc<dyn Shape>, SceneFileError> {
+	let (vector_properties, number_properties): (&[&str], &[&str]) = match keyword {
+	    "plane"    => (&["point", "normal"], &[]),
+	    "disc"     => (&["center", "normal"], &["radius"]),
+	    "quad"     => (&["corner", "u", "v"], &[]),
+	    "box"      => (&["min", "max", "center", "size", "rotate"], &[]),
+	    "cylinder" => (&["base", "top"], &["radius"]),
+	    "cone"     => (&["base", "apex"], &["radius"]),
+	    "torus"    => (&["center", "axis"], &["major", "minor"]),
+	    _ => unreachable!(),
+	};
+
+	let mut vectors = HashMap::new();
+	let mut numbers = HashMap::new();
+	let mut material = None;
+	while let Some(property) = statement.words.next() {
+	    if property == "material" {
+		material = Some(self.material(statement)?);
+	    } else if vector_properties.contains(&property) {
+		vectors.insert(property, statement.next_vector(&format!("{} {}", keyword, property))?);
+	    } else if number_properties.contains(&property) {
+		numbers.insert(property, statement.next_f32(&format!("{} {}", keyword, property))?);
+	    } else {
+		return Err(statement.unknown_property(keyword, property));
+	    }
+	}
+
+	let material = match material {
+	    Some(material) => material,
+	    None => return Err(statement.error(format!("the {} needs a material", keyword))),
+	};
+	let missing = |name: &str| statement.error(format!("the {} needs a {}", keyword, name));
+	let vector = |name: &str| vectors.get(name).copied().ok_or_else(|| missing(name));
+	let number = |name: &str| numbers.get(name).copied().ok_or_else(|| missing(name));
+	let positive = |name: &str| match number(name)? {
+	    value if value > 0f32 => Ok(value),
+	    _ => Err(statement.error(format!("the {} {} must be positive", keyword, name))),
+	};
+	let direction = |name: &str| match vector(name)? {
+	    value if value.norm2() > 0f32 => Ok(value),
+	    _ => Err(statement.error(format!("the {} {} can not be zero", keyword, name))),
+	};
+	let different = |from: &str, to: &str| {
+	    let (start, end) = (vector(from)?, vector(to)?);
+	    if (end - start).norm2() > 0f32 {
+		Ok((start, end))
+	    } else {
+		Err(statement.error(format!("the {} {} and {} can not be the same", keyword, from, to)))
+	    }
+	};
+
+	Ok(match keyword {
+	    "plane" => Arc::new(Plane::new(vector("point")?, direction("normal")?, material)),
+	    "disc" => Arc::new(Disc::new(vector("center")?, direction("normal")?, positive("radius")?, material)),
+	    "quad" => {
+		let quad = Parallelogram {
+		    corner: vector("corner")?,
+		    u: vector("u")?,
+		    v: vector("v")?,
+		    material,
+		};
+		if quad.area() <= 0f32 {
+		    return Err(statement.error(String::from("the quad has no area")));
+		}
+		Arc::new(quad)
+	    },
+	    "box" => {
+		let (center, size, rotation) = match (vectors.get("min"), vectors.get("max"), vectors.get("center"), vectors.get("size")) {
+		    (Some(&min), Some(&max), None, None) if !vectors.contains_key("rotate") => ((min + max) * 0.5f32, max - min, Vector { x: 0f32, y: 0f32, z: 0f32 }),
+		    (None, None, Some(&center), Some(&size)) => (center, size, vectors.get("rotate").copied().unwrap_or(Vector { x: 0f32, y: 0f32, z: 0f32 })),
+		    _ => return Err(statement.error(String::from("the box needs either a min and max or a center, size and optionally rotate"))),
+		};
+		if size.x <= 0f32 || size.y <= 0f32 || size.z <= 0f32 {
+		    return Err(statement.error(String::from("the box must have a positive size along every axis")));
+		}
+
+		let rotation = ModelTransform { rotation, ..ModelTransform::identity() }.transform();
+		let axes = Frame {
+		    s: rotation.apply_vector(Vector { x: 1f32, y: 0f32, z: 0f32 }),
+		    t: rotation.apply_vector(Vector { x: 0f32, y: 1f32, z: 0f32 }),
+		    n: rotation.apply_vector(Vector { x: 0f32, y: 0f32, z: 1f32 }),
+		};
+		Arc::new(Cuboid::new(center, axes, size, material))
+	    },
+	    "cylinder" => {
+		let (base, top) = different("base", "top")?;
+		Arc::new(Cylinder::new(base, top, positive("radius")?, material))
+	    },
+	    "cone" => {
+		let (base, apex) = different("base", "apex")?;
+		Arc::new(Cone::new(base, apex, positive("radius")?, material))
+	    },
+	    "torus" => {
+		let (major, minor) = (positive("major")?, positive("minor")?);
+		if minor >= major {
+		    return Err(statement.error(String::from("the torus minor radius must be smaller than its major radius")));
+		}
+		Arc::new(Torus::new(vector("center")?, direction("axis")?, major, minor, material))
+	    },
+	    _ => unreachable!(),
+	})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: &str = "camera position 0 -0.9 0.5 forward 0 1 0 right 1 0 0 up 0 0 1\n";
+
+    // the line and message of the error the scene fails with
+    fn error(text: &str) -> (Option<usize>, String) {
+	match parse_scene(text) {
+	    Ok(_) => panic!("the scene was accepted:\n{}", text),
+	    Err(error) => (error.line, error.message),
+	}
+    }
+
+    #[test]
+    fn nan_and_infinite_settings_are_rejected() {
+	for (statement, message) in [
+	    ("sphere position 0 0 0 radius nan color 1 1 1", "the sphere radius must be a positive number"),
+	    ("sphere position 0 0 0 radius inf color 1 1 1", "the sphere radius must be a positive number"),
+	    ("sphere position 0 0 0 radius 0 color 1 1 1", "the sphere radius must be a positive number"),
+	    ("exposure nan", "the exposure must be a positive number"),
+	    ("exposure inf", "the exposure must be a positive number"),
+	    ("gamma -1", "the gamma must be a positive number"),
+	    ("gamma NaN", "the gamma must be a positive number"),
+	    ("material bottle glass nan", "the index of refraction must be a positive number"),
+	    ("material bottle glass -inf", "the index of refraction must be a positive number"),
+	    ("material paint principled roughness 0.4 ior nan", "the index of refraction must be a positive number"),
+	    ("material paint principled ior inf", "the index of refraction must be a positive number"),
+	] {
+	    let text = format!("{}{}\n", CAMERA, statement);
+	    assert_eq!(error(&text), (Some(2), String::from(message)), "{}", statement);
+	}
+
+	let text = format!("{}exposure 25\ngamma 0.6\nsphere position 0 0 0 radius 0.5 color 1 1 1\n", CAMERA);
+	let description = parse_scene(&text).unwrap_or_else(|error| panic!("{}", error));
+	assert_eq!((description.exposure, description.gamma), (25f32, 0.6f32));
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+	// comments and empty lines count as lines as well
+	let text = "# a comment\n\nresolution 240 135\n   \nsamples ten\n";
+	assert_eq!(error(text), (Some(5), String::from("expected number of samples per pixel, found 'ten'")));
+
+	let text = format!("{}material red diffuse 0.9 0 0\ntriangle blue 1 1 0  -1 1 0  1 -1 0\n", CAMERA);
+	assert_eq!(error(&text).0, Some(3));
+
+	let text = format!("# camera below\n{}\nsphere position 0 0 1 radius 0.5\n", CAMERA);
+	assert_eq!(error(&text), (Some(4), String::from("the sphere needs a position, radius and a material or color")));
+
+	let text = format!("{}resolution 240 135 # fine\nresolution 240 135 16\n", CAMERA);
+	assert_eq!(error(&text), (Some(3), String::from("unexpected '16' at the end of the line")));
+
+	let text = format!("{}\n\nteapot\n", CAMERA);
+	assert_eq!(error(&text).0, Some(4));
+    }
+
+    #[test]
+    fn errors_about_the_whole_file_have_no_line() {
+	assert_eq!(error("resolution 240 135\n"), (None, String::from("the scene has no camera")));
+    }
+}
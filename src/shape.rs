@@ -0,0 +1,223 @@
+use std::fmt::Debug;
+use std::f32::consts::PI;
+
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::frame::*;
+
+// a point picked on the surface of a shape
+#[derive(Copy, Clone, Debug)]
+pub struct ShapeSample {
+    pub point: Vector,
+    pub normal: Vector, // geometric normal, on the front side of the surface
+    pub error: Vector, // bound on how far each coordinate of the point may be off through rounding
+    // with respect to area for Shape::sample_area, and to solid angle as seen from the position for Shape::sample
+    pub pdf: f32,
+}
+
+// geometry that rays can hit, which can act as a light source when its material is emissive
+pub trait Shape: Debug + Send + Sync {
+    // the closest hit within the range of the ray, the back of the surface included
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)>;
+
+    // whether the ray hits the shape within its range at a point where accept, given the geometric normal there,
+    // returns true, for shadow rays that do not need the rest of the surface element
+    fn occludes(&self, ray: Ray, accept: &dyn Fn(Vector) -> bool) -> bool {
+	self.intersect(ray).is_some_and(|(_, surface_element)| accept(surface_element.geometric_normal))
+    }
+
+    fn bounds(&self) -> BoundingBox;
+
+    fn area(&self) -> f32;
+
+    fn material(&self) -> usize; // index into Scene::materials
+
+    // maps p1 and p2, uniform in [0, 1), to a point uniformly distributed over the surface
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample;
+
+    // picks a point on the shape to be seen from position, None when it can not be seen,
+    // by area unless the shape has a better way
+    fn sample(&self, position: Vector, p1: f32, p2: f32) -> Option<ShapeSample> {
+	let sample = self.sample_area(p1, p2);
+	let pdf = self.pdf(position, sample.point, sample.normal);
+
+	if pdf > 0f32 {
+	    Some(ShapeSample { pdf, ..sample })
+	} else {
+	    None
+	}
+    }
+
+    // density with respect to solid angle of sample picking the point, with the given normal, from position
+    fn pdf(&self, position: Vector, point: Vector, normal: Vector) -> f32 {
+	uniform_area_pdf(self.area(), position, point, normal)
+    }
+}
+
+// density with respect to solid angle as seen from position of picking the point, with the given normal,
+// uniformly over a surface with the given area
+pub fn uniform_area_pdf(area: f32, position: Vector, point: Vector, normal: Vector) -> f32 {
+    let to_point = point - position;
+    let distance = to_point.norm();
+    if distance == 0f32 {
+	return 0f32;
+    }
+
+    area_to_solid_angle(1f32 / area, distance, dot(to_point, normal).abs() / distance)
+}
+
+// a density with respect to the area of a surface, changed into the solid angle it covers as seen from the given distance,
+// at which it is seen at an angle with the given cosine to its normal
+pub fn area_to_solid_angle(pdf: f32, distance: f32, cos_theta: f32) -> f32 {
+    if cos_theta <= 0f32 {
+	return 0f32;
+    }
+    pdf * distance * distance / cos_theta
+}
+
+// where a shape that is described in its own coordinates is placed in the scene, without scaling,
+// so lengths, areas and depths along rays are the same in both
+#[derive(Copy, Clone, Debug)]
+pub struct Placement {
+    pub origin: Vector,
+    pub frame: Frame, // the axes of the shape in the scene, its z axis is the normal of the frame
+}
+
+impl Placement {
+    // the z axis along the given axis, the others around it in any orientation
+    pub fn new(origin: Vector, axis: Vector) -> Placement {
+	Placement {
+	    origin,
+	    frame: Frame::new(axis),
+	}
+    }
+
+    // the ray in the coordinates of the shape, returned with the distance its origin was moved forward along it,
+    // which has to be added to the depths found with it, the rounding in moving the origin could otherwise
+    // take it back onto the surface the ray leaves
+    pub fn local_ray(self, ray: Ray) -> (Ray, f32) {
+	let magnitude = ray.origin.abs() + self.origin.abs();
+	let shift = rounding_error(5) * (magnitude.x + magnitude.y + magnitude.z);
+	let direction = self.frame.to_local(ray.direction).normalised();
+
+	let local_ray = Ray {
+	    origin: self.frame.to_local(ray.origin - self.origin) + direction * shift,
+	    direction,
+	    t_min: (ray.t_min - shift).max(0f32),
+	    t_max: ray.t_max - shift,
+	};
+	(local_ray, shift)
+    }
+
+    // a point in the coordinates of the shape, whose coordinates may be off by up to error there, moved into
+    // the scene, returned with the bound on the error of its coordinates there
+    pub fn point_to_world(self, point: Vector, error: Vector) -> (Vector, Vector) {
+	let Frame { s, t, n } = self.frame;
+	let magnitude = self.origin.abs() + s.abs() * point.x.abs() + t.abs() * point.y.abs() + n.abs() * point.z.abs();
+	let propagated = s.abs() * error.x + t.abs() * error.y + n.abs() * error.z;
+
+	(self.origin + self.frame.to_world(point), magnitude * rounding_error(4) + propagated * (1f32 + rounding_error(3)))
+    }
+
+    // the surface element of a hit found in the coordinates of the shape, with the point off by up to error there
+    pub fn surface_element(self, local: LocalHit, error: Vector, material: usize) -> SurfaceElement {
+	let (position, error) = self.point_to_world(local.point, error);
+	let normal = self.frame.to_world(local.normal).normalised();
+
+	SurfaceElement {
+	    position,
+	    error,
+	    normal,
+	    geometric_normal: normal,
+	    tangent: self.frame.to_world(local.tangent).normalised(),
+	    texture_coordinates: local.texture_coordinates,
+	    color: None,
+	    material,
+	}
+    }
+
+    // a point picked on a shape in its own coordinates, with the density it was picked with with respect to area
+    pub fn sample_to_world(self, point: Vector, normal: Vector, pdf: f32) -> ShapeSample {
+	let (point, error) = self.point_to_world(point, point.abs() * rounding_error(5));
+	ShapeSample {
+	    point,
+	    normal: self.frame.to_world(normal).normalised(),
+	    error,
+	    pdf,
+	}
+    }
+
+    // box around the eight corners of a box in the coordinates of the shape
+    pub fn bounds(self, min: Vector, max: Vector) -> BoundingBox {
+	let mut bounds = BoundingBox::empty();
+	for corner in 0 .. 8 {
+	    let (point, error) = self.point_to_world(Vector {
+		x: if corner & 1 == 0 { min.x } else { max.x },
+		y: if corner & 2 == 0 { min.y } else { max.y },
+		z: if corner & 4 == 0 { min.z } else { max.z },
+	    }, Vector { x: 0f32, y: 0f32, z: 0f32 });
+	    bounds = bounds.include(point - error).include(point + error);
+	}
+	bounds
+    }
+}
+
+// a hit in the coordinates of a shape, the normal on the outside and the tangent along increasing u
+#[derive(Copy, Clone, Debug)]
+pub struct LocalHit {
+    pub depth: f32,
+    pub point: Vector,
+    pub normal: Vector,
+    pub tangent: Vector,
+    pub texture_coordinates: (f32, f32),
+}
+
+// the closest of the hits within the range of the ray
+pub fn closest_local_hit(ray: Ray, hits: &[Option<LocalHit>]) -> Option<LocalHit> {
+    hits.iter()
+	.flatten()
+	.filter(|hit| hit.depth >= ray.t_min && hit.depth <= ray.t_max)
+	.fold(None, |closest: Option<LocalHit>, hit| match closest {
+	    Some(closest) if closest.depth <= hit.depth => Some(closest),
+	    _ => Some(*hit),
+	})
+}
+
+// the real roots of a t^2 + b t + c, smallest first, in double precision and without the cancellation
+// of the usual formula, so roots close to zero are found accurately as well
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0f64 {
+	if b == 0f64 {
+	    return None;
+	}
+	return Some((-c / b, -c / b));
+    }
+
+    let discriminant = b * b - 4f64 * a * c;
+    if discriminant < 0f64 {
+	return None;
+    }
+
+    let q = if b < 0f64 { -0.5f64 * (b - discriminant.sqrt()) } else { -0.5f64 * (b + discriminant.sqrt()) };
+    if q == 0f64 {
+	return Some((0f64, 0f64));
+    }
+
+    let (t0, t1) = (q / a, c / q);
+    Some(if t0 <= t1 { (t0, t1) } else { (t1, t0) })
+}
+
+// maps p1 and p2, uniform in [0, 1), to a point uniformly distributed over the unit disc
+pub fn sample_unit_disc(p1: f32, p2: f32) -> (f32, f32) {
+    let r = p1.sqrt();
+    let phi = 2f32 * PI * p2;
+    (r * phi.cos(), r * phi.sin())
+}
+
+// the angle of (x, y) counterclockwise from the x axis, as a fraction of a full turn from 0 to 1
+pub fn turn_fraction(x: f32, y: f32) -> f32 {
+    let phi = y.atan2(x) / (2f32 * PI);
+    if phi < 0f32 { phi + 1f32 } else { phi }
+}
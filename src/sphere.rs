@@ -1,40 +1,265 @@
 use crate::vector::*;
 use crate::ray::*;
-use crate::color::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::shape::*;
 
+use std::f32::consts::PI;
+
+// below this the cone of directions towards a sphere is so narrow that 1 - cos theta loses its precision,
+// and is found from the taylor expansion instead, this is about the sine squared of 1.5 degrees
+const SMALL_CONE_SIN2: f32 = 0.00068523f32;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Sphere {
     pub position: Vector,
     pub radius: f32,
-    pub color: Color,
+    pub material: usize, // index into Scene::materials
 }
 
 impl Sphere {
-    pub fn intersect(self, ray: Ray) -> Option<f32> {
+    // the point on the sphere in the given direction from its center, which does not have to be a unit vector,
+    // so the rounding in finding the direction does not move the point off the sphere
+    fn project(self, direction: Vector) -> Vector {
+	self.position + direction * (self.radius / direction.norm())
+    }
+
+    // bound on the rounding error of a point found by project
+    fn position_error(self, point: Vector) -> Vector {
+	(self.position.abs() + (point - self.position).abs()) * rounding_error(6)
+    }
+
+    // inside the sphere or too near its surface for the cone of directions towards it to be found accurately
+    fn is_close(self, position: Vector) -> bool {
+	(position - self.position).norm2() <= self.radius * self.radius * 1.0001f32
+    }
+
+    fn cone_pdf(self, sin2_theta_max: f32) -> f32 {
+	1f32 / (2f32 * PI * one_minus_cos_theta_max(sin2_theta_max))
+    }
+
+    fn sample_at(self, point: Vector, pdf: f32) -> ShapeSample {
+	ShapeSample {
+	    point,
+	    normal: (point - self.position) * (1f32 / self.radius),
+	    error: self.position_error(point),
+	    pdf,
+	}
+    }
+}
+
+impl Shape for Sphere {
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
 	assert!(ray.direction.is_normal());
 
 	let relative_origin = ray.origin - self.position;
-	
-	// a = 1, so not computed
-	let b = 2f32 * dot(relative_origin, ray.direction);
-	let c = relative_origin.norm2() - self.radius * self.radius;
+	let (o, d) = (relative_origin, ray.direction);
+
+	let a = d.x as f64 * d.x as f64 + d.y as f64 * d.y as f64 + d.z as f64 * d.z as f64;
+	let b = 2f64 * (o.x as f64 * d.x as f64 + o.y as f64 * d.y as f64 + o.z as f64 * d.z as f64);
+	let c = o.x as f64 * o.x as f64 + o.y as f64 * o.y as f64 + o.z as f64 * o.z as f64 - self.radius as f64 * self.radius as f64;
+	let (t0, t1) = solve_quadratic(a, b, c)?;
+
+	// the far side is hit when the ray starts inside
+	let distance = [t0, t1].iter()
+	    .map(|&t| t as f32)
+	    .find(|&t| t >= ray.t_min && t <= ray.t_max)?;
+
+	let position = self.project(relative_origin + ray.direction * distance);
+	let normal = (position - self.position) * (1f32 / self.radius);
+
+	// u goes around the z axis counterclockwise starting at x, v from the bottom to the top
+	let phi = normal.y.atan2(normal.x);
+	let u = if phi < 0f32 { phi / (2f32 * PI) + 1f32 } else { phi / (2f32 * PI) };
+	let v = (-normal.z).clamp(-1f32, 1f32).acos() / PI;
+
+	// the direction of increasing u is undefined at the poles
+	let tangent = Vector { x: -normal.y, y: normal.x, z: 0f32 };
+	let tangent = if tangent.norm2() > 0f32 { tangent.normalised() } else { normal.make_orthogonal_frame().0 };
+
+	Some((
+	    distance,
+	    SurfaceElement {
+		position,
+		error: self.position_error(position),
+		normal,
+		geometric_normal: normal,
+		tangent,
+		texture_coordinates: (u, v),
+		color: None,
+		material: self.material,
+	    }))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	let extent = Vector { x: self.radius, y: self.radius, z: self.radius };
+	BoundingBox::empty()
+	    .include(self.position - extent)
+	    .include(self.position + extent)
+    }
+
+    fn area(&self) -> f32 {
+	4f32 * PI * self.radius * self.radius
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	// 1 - z^2 = 4 p1 (1 - p1), which unlike the difference keeps its precision near the poles
+	let z = 1f32 - 2f32 * p1;
+	let r = 2f32 * (p1 * (1f32 - p1)).max(0f32).sqrt();
+	let phi = 2f32 * PI * p2;
+	let point = self.project(Vector { x: r * phi.cos(), y: r * phi.sin(), z });
+
+	self.sample_at(point, 1f32 / self.area())
+    }
+
+    // uniformly over the cone of directions in which the sphere is seen from position, or by area when the position
+    // is inside, where the whole surface is seen, or too close to the surface for the cone to be found accurately
+    fn sample(&self, position: Vector, p1: f32, p2: f32) -> Option<ShapeSample> {
+	if self.is_close(position) {
+	    let sample = self.sample_area(p1, p2);
+	    let pdf = self.pdf(position, sample.point, sample.normal);
+	    return if pdf > 0f32 { Some(ShapeSample { pdf, ..sample }) } else { None };
+	}
+
+	let to_center = self.position - position;
+	let distance2 = to_center.norm2();
+	let axis = to_center * (1f32 / distance2.sqrt());
 
-	let discriminant = b * b - 4f32 * c;
+	// theta is the angle between the axis and the direction, from 0 up to where the direction grazes the sphere
+	let sin2_theta_max = self.radius * self.radius / distance2;
+	let (cos_theta, sin2_theta) = if sin2_theta_max < SMALL_CONE_SIN2 {
+	    let sin2_theta = sin2_theta_max * p1;
+	    ((1f32 - sin2_theta).sqrt(), sin2_theta)
+	} else {
+	    let cos_theta = 1f32 - p1 * one_minus_cos_theta_max(sin2_theta_max);
+	    (cos_theta, (1f32 - cos_theta * cos_theta).max(0f32))
+	};
 
-	if discriminant < 0f32 {
-	    return None;
+	// alpha is the angle at the center of the sphere between the axis and the point the direction hits first
+	let cos_alpha = sin2_theta / sin2_theta_max.sqrt() + cos_theta * (1f32 - sin2_theta / sin2_theta_max).max(0f32).sqrt();
+	let sin_alpha = (1f32 - cos_alpha * cos_alpha).max(0f32).sqrt();
+	let phi = 2f32 * PI * p2;
+
+	let (v1, v2) = axis.make_orthogonal_frame();
+	let normal = -axis * cos_alpha + (v1 * phi.cos() + v2 * phi.sin()) * sin_alpha;
+
+	Some(self.sample_at(self.project(normal), self.cone_pdf(sin2_theta_max)))
+    }
+
+    fn pdf(&self, position: Vector, point: Vector, normal: Vector) -> f32 {
+	if self.is_close(position) {
+	    return uniform_area_pdf(self.area(), position, point, normal);
+	}
+
+	let distance2 = (self.position - position).norm2();
+	self.cone_pdf(self.radius * self.radius / distance2)
+    }
+}
+
+fn one_minus_cos_theta_max(sin2_theta_max: f32) -> f32 {
+    if sin2_theta_max < SMALL_CONE_SIN2 {
+	0.5f32 * sin2_theta_max
+    } else {
+	1f32 - (1f32 - sin2_theta_max).max(0f32).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIANCE: f32 = 2f32;
+
+    // irradiance at position on a surface with the given normal from the sphere emitting RADIANCE, the estimate
+    // of sample integrated over the given values of p1, each with the width of its stratum, and n values of p2,
+    // from outside only the points whose front is seen count
+    fn irradiance(sphere: &Sphere, position: Vector, normal: Vector, p1_strata: &[(f32, f32)], n: usize) -> f32 {
+	let inside = (position - sphere.position).norm() < sphere.radius;
+	let mut sum = 0f64;
+	for &(p1, width) in p1_strata {
+	    for j in 0 .. n {
+		let p2 = (j as f32 + 0.5f32) / n as f32;
+		let sample = match sphere.sample(position, p1, p2) {
+		    Some(sample) => sample,
+		    None => continue,
+		};
+
+		let direction = (sample.point - position).normalised();
+		if !inside && dot(direction, sample.normal) >= 0f32 {
+		    continue;
+		}
+		sum += (RADIANCE * dot(direction, normal).max(0f32) / sample.pdf * width) as f64 / n as f64;
+	    }
+	}
+	sum as f32
+    }
+
+    fn uniform_strata(n: usize) -> Vec<(f32, f32)> {
+	(0 .. n).map(|i| ((i as f32 + 0.5f32) / n as f32, 1f32 / n as f32)).collect()
+    }
+
+    // from low to high, evenly spaced in the logarithm
+    fn logarithmic_strata(low: f64, high: f64, n: usize) -> Vec<(f32, f32)> {
+	let step = (high / low).ln() / n as f64;
+	(0 .. n).map(|i| {
+	    let p1 = low * ((i as f64 + 0.5f64) * step).exp();
+	    (p1 as f32, (p1 * step) as f32)
+	}).collect()
+    }
+
+    // pi L sin^2 theta_max, for a sphere entirely above the horizon
+    fn analytic_irradiance(sphere: &Sphere, position: Vector) -> f32 {
+	let sin2_theta_max = (sphere.radius * sphere.radius / (position - sphere.position).norm2()).min(1f32);
+	PI * RADIANCE * sin2_theta_max
+    }
+
+    fn sphere() -> Sphere {
+	Sphere {
+	    position: Vector { x: 0.5f32, y: -0.25f32, z: 1f32 },
+	    radius: 0.5f32,
+	    material: 0,
 	}
+    }
+
+    fn assert_close(estimate: f32, expected: f32) {
+	assert!((estimate / expected - 1f32).abs() < 0.01f32, "estimated {}, expected {}", estimate, expected);
+    }
 
-	let distance = 0.5f32 * (-b - discriminant.sqrt());
+    #[test]
+    fn far_outside_matches_analytic_irradiance() {
+	let sphere = sphere();
+	for distance in [1.5f32, 5f32, 40f32].iter() {
+	    let position = sphere.position - Vector { x: 0.6f32, y: 0f32, z: 0.8f32 } * *distance;
+	    assert!(!sphere.is_close(position));
+	    let normal = (sphere.position - position).normalised();
 
-	if distance < 0f32 {
-	    return None;
+	    assert_close(irradiance(&sphere, position, normal, &uniform_strata(256), 256), analytic_irradiance(&sphere, position));
 	}
+    }
+
+    #[test]
+    fn near_surface_matches_analytic_irradiance() {
+	// right above the top of the sphere, where it is sampled by area, only the points with p1 below (1 - r / d) / 2,
+	// on the cap around the top that is seen from there, count, most of all those right below, with tiny p1
+	let sphere = sphere();
+	let position = sphere.position + Vector { x: 0f32, y: 0f32, z: sphere.radius * 1.00004f32 };
+	assert!(sphere.is_close(position));
+	let normal = Vector { x: 0f32, y: 0f32, z: -1f32 };
+
+	assert_close(irradiance(&sphere, position, normal, &logarithmic_strata(1e-16f64, 1e-4f64, 2000), 4), analytic_irradiance(&sphere, position));
+    }
+
+    #[test]
+    fn inside_matches_analytic_irradiance() {
+	// the sphere is seen in every direction, sin^2 theta_max is one
+	let sphere = sphere();
+	let position = sphere.position + Vector { x: 0.2f32, y: 0.1f32, z: -0.15f32 };
+	let normal = Vector { x: 0.6f32, y: 0f32, z: 0.8f32 };
 
-	//let intersection_point = ray.origin + ray.direction * distance;
-	//let normal = (intersection_point - self.position).normalised();
-	
-	Some(distance)
+	assert_close(irradiance(&sphere, position, normal, &uniform_strata(256), 256), analytic_irradiance(&sphere, position));
     }
 }
@@ -1,9 +1,14 @@
 use crate::vector::*;
-use crate::material::*;
+use crate::color::*;
 
 #[derive(Copy, Clone, Debug)]
 pub struct SurfaceElement {
     pub position: Vector,
-    pub normal: Vector, // must be a unit vector
-    pub material: Material,
+    pub error: Vector, // bound on how far each coordinate of the position may be off through rounding
+    pub normal: Vector, // for shading, must be a unit vector
+    pub geometric_normal: Vector, // of the surface itself, the one to use for deciding which side a ray is on
+    pub tangent: Vector, // unit vector along the surface in the direction of increasing u, for orienting anisotropic materials
+    pub texture_coordinates: (f32, f32),
+    pub color: Option<Color>, // interpolated vertex color, if the surface has them
+    pub material: usize, // index into Scene::materials
 }
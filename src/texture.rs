@@ -0,0 +1,61 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::vector::*;
+use crate::color::*;
+use crate::surface_element::*;
+
+// color that varies over a surface, textures can be built out of other textures
+pub trait Texture: Debug + Send + Sync {
+    fn color(&self, surface_element: &SurfaceElement) -> Color;
+}
+
+// a constant color is the simplest texture
+impl Texture for Color {
+    fn color(&self, _surface_element: &SurfaceElement) -> Color {
+	*self
+    }
+}
+
+// two textures multiplied, such as an image tinted by a color
+#[derive(Debug)]
+pub struct ProductTexture {
+    pub first: Arc<dyn Texture>,
+    pub second: Arc<dyn Texture>,
+}
+
+impl Texture for ProductTexture {
+    fn color(&self, surface_element: &SurfaceElement) -> Color {
+	self.first.color(surface_element) * self.second.color(surface_element)
+    }
+}
+
+// what procedural textures are evaluated at
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mapping {
+    Uv,       // the texture coordinates, as (u, v, 0)
+    Position, // the position in the scene, so the texture is carved out of a solid block
+}
+
+impl Mapping {
+    // names as used in scene files
+    pub const NAMES: [&'static str; 2] = ["uv", "position"];
+
+    pub fn from_name(name: &str) -> Option<Mapping> {
+	match name {
+	    "uv"       => Some(Mapping::Uv),
+	    "position" => Some(Mapping::Position),
+	    _ => None,
+	}
+    }
+
+    pub fn point(self, surface_element: &SurfaceElement) -> Vector {
+	match self {
+	    Mapping::Uv => {
+		let (u, v) = surface_element.texture_coordinates;
+		Vector { x: u, y: v, z: 0f32 }
+	    },
+	    Mapping::Position => surface_element.position,
+	}
+    }
+}
@@ -0,0 +1,195 @@
+use crate::vector::*;
+use crate::ray::*;
+use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::shape::*;
+
+use std::f32::consts::PI;
+
+// ring with a round cross section, the points at distance minor_radius from the circle of radius major_radius
+// around the z axis of its placement, seen from the outside, major_radius must be larger than minor_radius
+#[derive(Copy, Clone, Debug)]
+pub struct Torus {
+    pub placement: Placement, // origin at the center, z axis along the axis of the ring
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: usize, // index into Scene::materials
+}
+
+impl Torus {
+    pub fn new(center: Vector, axis: Vector, major_radius: f32, minor_radius: f32, material: usize) -> Torus {
+	Torus {
+	    placement: Placement::new(center, axis),
+	    major_radius,
+	    minor_radius,
+	    material,
+	}
+    }
+
+    // the point on the circle in the middle of the tube that is closest to the given point
+    fn ring_point(self, point: Vector) -> Vector {
+	let distance = (point.x * point.x + point.y * point.y).sqrt();
+	if distance > 0f32 {
+	    Vector { x: point.x * self.major_radius / distance, y: point.y * self.major_radius / distance, z: 0f32 }
+	} else {
+	    Vector { x: self.major_radius, y: 0f32, z: 0f32 }
+	}
+    }
+
+    // the hit in the coordinates of the torus, where u goes around the axis and v around the tube,
+    // starting on the outside of the ring
+    fn intersect_local(self, ray: Ray) -> Option<LocalHit> {
+	let (major, minor) = (self.major_radius as f64, self.minor_radius as f64);
+
+	// only the part of the ray inside the sphere around the torus is searched, and the depths are counted from
+	// where that part starts, which keeps the coefficients small for rays that start far away
+	let origin = [ray.origin.x as f64, ray.origin.y as f64, ray.origin.z as f64];
+	let direction = [ray.direction.x as f64, ray.direction.y as f64, ray.direction.z as f64];
+	let o_dot_d = origin[0] * direction[0] + origin[1] * direction[1] + origin[2] * direction[2];
+	let o_dot_o = origin[0] * origin[0] + origin[1] * origin[1] + origin[2] * origin[2];
+	let d_dot_d = direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2];
+	let (sphere_enter, sphere_exit) = solve_quadratic(d_dot_d, 2f64 * o_dot_d, o_dot_o - (major + minor) * (major + minor))?;
+	let start = sphere_enter.max(ray.t_min as f64);
+	let end = sphere_exit.min(ray.t_max as f64);
+	if start > end {
+	    return None;
+	}
+	let o = [origin[0] + direction[0] * start, origin[1] + direction[1] * start, origin[2] + direction[2] * start];
+	let d = direction;
+
+	// (|p|^2 + R^2 - r^2)^2 = 4 R^2 (px^2 + py^2) for the point p = o + t d, with |d| = 1
+	let e = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] + major * major - minor * minor;
+	let f = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+	let four_major2 = 4f64 * major * major;
+	let coefficients = [
+	    e * e - four_major2 * (o[0] * o[0] + o[1] * o[1]),
+	    4f64 * e * f - 2f64 * four_major2 * (o[0] * d[0] + o[1] * d[1]),
+	    4f64 * f * f + 2f64 * e - four_major2 * (d[0] * d[0] + d[1] * d[1]),
+	    4f64 * f,
+	    1f64,
+	];
+
+	let t = smallest_root(&coefficients, 0f64, end - start)?;
+	let depth = (start + t) as f32;
+	if depth < ray.t_min || depth > ray.t_max {
+	    return None;
+	}
+
+	// moved onto the surface, away from the circle in the middle of the tube
+	let hit = ray.origin + ray.direction * depth;
+	let ring_point = self.ring_point(hit);
+	let to_hit = hit - ring_point;
+	let normal = if to_hit.norm2() > 0f32 { to_hit.normalised() } else { Vector { x: 0f32, y: 0f32, z: 1f32 } };
+	let point = ring_point + normal * self.minor_radius;
+
+	let outwards = (point.x * point.x + point.y * point.y).sqrt() - self.major_radius;
+	Some(LocalHit {
+	    depth,
+	    point,
+	    normal,
+	    tangent: Vector { x: -ring_point.y, y: ring_point.x, z: 0f32 },
+	    texture_coordinates: (turn_fraction(point.x, point.y), turn_fraction(outwards, point.z)),
+	})
+    }
+}
+
+impl Shape for Torus {
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	let (local_ray, shift) = self.placement.local_ray(ray);
+	let local = self.intersect_local(local_ray)?;
+
+	Some((local.depth + shift, self.placement.surface_element(local, local.point.abs() * rounding_error(8), self.material)))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	let extent = Vector {
+	    x: self.major_radius + self.minor_radius,
+	    y: self.major_radius + self.minor_radius,
+	    z: self.minor_radius,
+	};
+	self.placement.bounds(-extent, extent)
+    }
+
+    fn area(&self) -> f32 {
+	4f32 * PI * PI * self.major_radius * self.minor_radius
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    // the outside of the ring has more area than the inside, the angle v around the tube has a density
+    // proportional to R + r cos v, whose cumulative distribution (R v + r sin v) / (2 pi R) is inverted numerically
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let (major, minor) = (self.major_radius, self.minor_radius);
+	let target = 2f32 * PI * major * p2;
+
+	// newton's method, falling back on bisection when a step leaves the interval known to hold the angle
+	let (mut low, mut high) = (0f32, 2f32 * PI);
+	let mut v = 2f32 * PI * p2;
+	for _ in 0 .. 16 {
+	    let error = major * v + minor * v.sin() - target;
+	    if error > 0f32 { high = v } else { low = v }
+	    let next = v - error / (major + minor * v.cos());
+	    v = if next > low && next < high { next } else { 0.5f32 * (low + high) };
+	}
+
+	let u = 2f32 * PI * p1;
+	let normal = Vector { x: v.cos() * u.cos(), y: v.cos() * u.sin(), z: v.sin() };
+	let point = Vector { x: major * u.cos(), y: major * u.sin(), z: 0f32 } + normal * minor;
+
+	self.placement.sample_to_world(point, normal, 1f32 / self.area())
+    }
+}
+
+// the smallest root of the polynomial with the given coefficients, lowest degree first, between low and high,
+// the roots of its derivative split the interval into pieces on which it is monotonic, and each piece holds a root
+// when the polynomial changes sign over it, which bisection then finds
+fn smallest_root(coefficients: &[f64], low: f64, high: f64) -> Option<f64> {
+    monotonic_pieces(coefficients, low, high).windows(2)
+	.find_map(|piece| bisect(coefficients, piece[0], piece[1]))
+}
+
+// the ends of the interval with the roots of the derivative in between, in order
+fn monotonic_pieces(coefficients: &[f64], low: f64, high: f64) -> Vec<f64> {
+    let mut bounds = vec![low];
+    if coefficients.len() > 2 {
+	let derivative: Vec<f64> = coefficients.iter().enumerate().skip(1).map(|(power, c)| c * power as f64).collect();
+	let mut start = low;
+	for piece in monotonic_pieces(&derivative, low, high).windows(2) {
+	    if let Some(root) = bisect(&derivative, piece[0], piece[1]) {
+		if root > start {
+		    bounds.push(root);
+		    start = root;
+		}
+	    }
+	}
+    }
+    bounds.push(high);
+    bounds
+}
+
+// the root of a polynomial that is monotonic between low and high, if it has one there
+fn bisect(coefficients: &[f64], low: f64, high: f64) -> Option<f64> {
+    let (mut low, mut high) = (low, high);
+    let (value_low, value_high) = (evaluate(coefficients, low), evaluate(coefficients, high));
+    if value_low == 0f64 {
+	return Some(low);
+    }
+    if value_low * value_high > 0f64 {
+	return None;
+    }
+
+    let rising = value_low < 0f64;
+    loop {
+	let middle = 0.5f64 * (low + high);
+	if middle <= low || middle >= high {
+	    return Some(middle);
+	}
+	if (evaluate(coefficients, middle) < 0f64) == rising { low = middle } else { high = middle }
+    }
+}
+
+fn evaluate(coefficients: &[f64], t: f64) -> f64 {
+    coefficients.iter().rev().fold(0f64, |value, c| value * t + c)
+}
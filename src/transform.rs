@@ -0,0 +1,201 @@
+use std::ops::*;
+
+use crate::vector::*;
+use crate::bounding_box::*;
+use crate::ray::*;
+
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1f32, 0f32, 0f32, 0f32],
+    [0f32, 1f32, 0f32, 0f32],
+    [0f32, 0f32, 1f32, 0f32],
+    [0f32, 0f32, 0f32, 1f32],
+];
+
+// affine transformation as a 4x4 matrix, acting on column vectors
+// the inverse is kept alongside, so it never has to be computed from the matrix
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub matrix: Matrix,
+    pub inverse: Matrix,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+	Transform {
+	    matrix: IDENTITY,
+	    inverse: IDENTITY,
+	}
+    }
+
+    pub fn translation(offset: Vector) -> Transform {
+	Transform {
+	    matrix: [
+		[1f32, 0f32, 0f32, offset.x],
+		[0f32, 1f32, 0f32, offset.y],
+		[0f32, 0f32, 1f32, offset.z],
+		[0f32, 0f32, 0f32, 1f32],
+	    ],
+	    inverse: [
+		[1f32, 0f32, 0f32, -offset.x],
+		[0f32, 1f32, 0f32, -offset.y],
+		[0f32, 0f32, 1f32, -offset.z],
+		[0f32, 0f32, 0f32, 1f32],
+	    ],
+	}
+    }
+
+    // per axis, none of the factors can be zero
+    pub fn scaling(factors: Vector) -> Transform {
+	assert!(factors.x != 0f32 && factors.y != 0f32 && factors.z != 0f32);
+
+	Transform {
+	    matrix: [
+		[factors.x, 0f32, 0f32, 0f32],
+		[0f32, factors.y, 0f32, 0f32],
+		[0f32, 0f32, factors.z, 0f32],
+		[0f32, 0f32, 0f32, 1f32],
+	    ],
+	    inverse: [
+		[1f32 / factors.x, 0f32, 0f32, 0f32],
+		[0f32, 1f32 / factors.y, 0f32, 0f32],
+		[0f32, 0f32, 1f32 / factors.z, 0f32],
+		[0f32, 0f32, 0f32, 1f32],
+	    ],
+	}
+    }
+
+    // counterclockwise around the axis (0 for x, 1 for y, 2 for z) when looking down from its positive end
+    pub fn rotation(axis: usize, degrees: f32) -> Transform {
+	assert!(axis < 3);
+
+	let (sin, cos) = degrees.to_radians().sin_cos();
+
+	// the two axes that are rotated into each other, a quarter turn takes the first to the second
+	let a = (axis + 1) % 3;
+	let b = (axis + 2) % 3;
+
+	let mut matrix = IDENTITY;
+	matrix[a][a] = cos;
+	matrix[a][b] = -sin;
+	matrix[b][a] = sin;
+	matrix[b][b] = cos;
+
+	// the inverse of a rotation is its transpose
+	Transform {
+	    matrix,
+	    inverse: transpose(matrix),
+	}
+    }
+
+    pub fn inverse(self) -> Transform {
+	Transform {
+	    matrix: self.inverse,
+	    inverse: self.matrix,
+	}
+    }
+
+    pub fn apply_point(self, point: Vector) -> Vector {
+	let m = self.matrix;
+	Vector {
+	    x: m[0][0] * point.x + m[0][1] * point.y + m[0][2] * point.z + m[0][3],
+	    y: m[1][0] * point.x + m[1][1] * point.y + m[1][2] * point.z + m[1][3],
+	    z: m[2][0] * point.x + m[2][1] * point.y + m[2][2] * point.z + m[2][3],
+	}
+    }
+
+    // bound on how far each coordinate of apply_point(point) may be off, for a point whose coordinates were
+    // already off by up to error, the rounding of apply_point itself included
+    pub fn apply_point_error(self, point: Vector, error: Vector) -> Vector {
+	let m = self.matrix;
+	let row = |i: usize| {
+	    let rounding = (m[i][0] * point.x).abs() + (m[i][1] * point.y).abs() + (m[i][2] * point.z).abs() + m[i][3].abs();
+	    let propagated = m[i][0].abs() * error.x + m[i][1].abs() * error.y + m[i][2].abs() * error.z;
+	    rounding_error(3) * rounding + (1f32 + rounding_error(3)) * propagated
+	};
+
+	Vector {
+	    x: row(0),
+	    y: row(1),
+	    z: row(2),
+	}
+    }
+
+    // directions and offsets, which are not affected by translation
+    pub fn apply_vector(self, vector: Vector) -> Vector {
+	let m = self.matrix;
+	Vector {
+	    x: m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+	    y: m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+	    z: m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+	}
+    }
+
+    // normals are transformed by the inverse transpose, so they stay perpendicular to the surface
+    // the result is not normalised
+    pub fn apply_normal(self, normal: Vector) -> Vector {
+	let m = self.inverse;
+	Vector {
+	    x: m[0][0] * normal.x + m[1][0] * normal.y + m[2][0] * normal.z,
+	    y: m[0][1] * normal.x + m[1][1] * normal.y + m[2][1] * normal.z,
+	    z: m[0][2] * normal.x + m[1][2] * normal.y + m[2][2] * normal.z,
+	}
+    }
+
+    // box around the transformed corners of the given box
+    pub fn apply_bounds(self, bounds: BoundingBox) -> BoundingBox {
+	let mut transformed = BoundingBox::empty();
+	for corner in 0 .. 8 {
+	    transformed = transformed.include(self.apply_point(Vector {
+		x: if corner & 1 == 0 { bounds.min.x } else { bounds.max.x },
+		y: if corner & 2 == 0 { bounds.min.y } else { bounds.max.y },
+		z: if corner & 4 == 0 { bounds.min.z } else { bounds.max.z },
+	    }));
+	}
+	transformed
+    }
+
+    // whether the transform turns shapes inside out, as reflections do
+    pub fn mirrors(self) -> bool {
+	let m = self.matrix;
+	let determinant =
+	    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) -
+	    m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0]) +
+	    m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+	determinant < 0f32
+    }
+}
+
+// composition, (a * b) first applies b and then a
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+	Transform {
+	    matrix: multiply(self.matrix, other.matrix),
+	    inverse: multiply(other.inverse, self.inverse),
+	}
+    }
+}
+
+fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    let mut product = [[0f32; 4]; 4];
+    for (i, row) in product.iter_mut().enumerate() {
+	for (j, element) in row.iter_mut().enumerate() {
+	    *element = (0 .. 4).map(|k| a[i][k] * b[k][j]).sum();
+	}
+    }
+    product
+}
+
+fn transpose(matrix: Matrix) -> Matrix {
+    let mut transposed = [[0f32; 4]; 4];
+    for (i, row) in transposed.iter_mut().enumerate() {
+	for (j, element) in row.iter_mut().enumerate() {
+	    *element = matrix[j][i];
+	}
+    }
+    transposed
+}
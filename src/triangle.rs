@@ -1,75 +1,520 @@
 use crate::vector::*;
-use crate::material::*;
+use crate::color::*;
 use crate::ray::*;
 use crate::surface_element::*;
+use crate::bounding_box::*;
+use crate::shape::*;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Triangle {
-    pub base: Vector,
-    pub v1: Vector,
-    pub v2: Vector,
-    pub material: Material,
+    // counterclockwise when seen from the front, triangles sharing an edge must have the exact same vertices there
+    pub vertices: [Vector; 3],
+    // unit normals at the vertices for smooth shading, None for a flat triangle
+    pub normals: Option<[Vector; 3]>,
+    // per vertex as well, without them the texture coordinates are (0, 0), (1, 0) and (0, 1)
+    pub texture_coordinates: Option<[(f32, f32); 3]>,
+    pub colors: Option<[Color; 3]>,
+    pub material: usize, // index into Scene::materials
 }
 
 impl Triangle {
+    // from the first vertex to the other two
+    pub fn edges(self) -> (Vector, Vector) {
+	let [p0, p1, p2] = self.vertices;
+	(p1 - p0, p2 - p0)
+    }
+
     pub fn normal(self) -> Vector {
-	cross(self.v1, self.v2).normalised()
+	let (e1, e2) = self.edges();
+	cross(e1, e2).normalised()
     }
 
-    pub fn intersect(self, ray: Ray) -> Option<(f32, SurfaceElement)> {
-	assert!(ray.direction.is_normal());
-	
-	let normal = self.normal();
-	assert!(normal.is_normal());
-
-	if dot(normal, ray.direction) > 0f32 { return None; } // face culling
-	
-	let relative_origin = ray.origin - self.base;
-	
-	let depth = -dot(relative_origin, normal) / dot (ray.direction, normal);
-	if depth < 0f32 { return None; }
-	let relative_intersection_point = relative_origin + ray.direction * depth;
+    // the point with the given barycentric coordinates, which add up to one
+    fn point(self, [b0, b1, b2]: [f32; 3]) -> Vector {
+	let [p0, p1, p2] = self.vertices;
+	p0 * b0 + p1 * b1 + p2 * b2
+    }
 
-	let a1 = dot(relative_intersection_point, self.v1);
-	let a2 = dot(relative_intersection_point, self.v2);
+    // bound on the rounding error of any point found from barycentric coordinates,
+    // the exact point lies in the plane of the triangle
+    fn position_error(self) -> Vector {
+	let [p0, p1, p2] = self.vertices;
+	(p0.abs() + p1.abs() + p2.abs()) * rounding_error(7)
+    }
 
-	let b11 = dot(self.v1, self.v1);
-	let b12 = dot(self.v1, self.v2);
-	let b22 = dot(self.v2, self.v2);
+    // watertight intersection (Woop, Benthin and Wald), returns the depth and the barycentric coordinates of the hit,
+    // which add up to one within a few ulps, and a bound on the rounding error of each coordinate of the point they give,
+    // a ray through an edge or vertex shared by several triangles hits at least one of them
+    // back faces are hit as well, it is up to the caller to ignore them for surfaces that can only be seen from the front
+    pub fn intersect_barycentric(self, ray: Ray) -> Option<(f32, [f32; 3], Vector)> {
+	// the vertices are moved into a space where the ray starts at the origin and runs along the z axis,
+	// made up of a translation, a permutation of the axes that makes z the largest component of the direction,
+	// and a shear, which keeps the relative rounding errors small
+	let direction = ray.direction.abs();
+	let kz = if direction.x > direction.y && direction.x > direction.z {
+	    0
+	} else if direction.y > direction.z {
+	    1
+	} else {
+	    2
+	};
+	let kx = (kz + 1) % 3;
+	let ky = (kx + 1) % 3;
+	let permute = |v: Vector| Vector { x: v.component(kx), y: v.component(ky), z: v.component(kz) };
 
-	let det = b11 * b22 - b12 * b12;
-	
-	let c1 = (a1 * b22 - a2 * b12) / det;
-	let c2 = (a2 * b11 - a1 * b12) / det;
+	let d = permute(ray.direction);
+	let shear_x = -d.x / d.z;
+	let shear_y = -d.y / d.z;
+	let shear_z = 1f32 / d.z;
 
-	if c1 < -0.0000001f32 || c2 < -0.0000001f32 || c1 + c2 > 1.0000001f32 {
+	let [p0, p1, p2] = self.vertices.map(|vertex| {
+	    let p = permute(vertex - ray.origin);
+	    Vector { x: p.x + shear_x * p.z, y: p.y + shear_y * p.z, z: p.z }
+	});
+
+	// twice the signed areas of the triangles the origin forms with each edge, in the plane the ray runs through
+	let edge_function = |a: Vector, b: Vector| {
+	    let e = a.x * b.y - a.y * b.x;
+	    if e != 0f32 {
+		return e;
+	    }
+	    // exactly on the edge in single precision, double precision settles which side
+	    (a.x as f64 * b.y as f64 - a.y as f64 * b.x as f64) as f32
+	};
+	let e0 = edge_function(p1, p2);
+	let e1 = edge_function(p2, p0);
+	let e2 = edge_function(p0, p1);
+
+	if (e0 < 0f32 || e1 < 0f32 || e2 < 0f32) && (e0 > 0f32 || e1 > 0f32 || e2 > 0f32) {
 	    return None;
 	}
+	let det = e0 + e1 + e2;
+	if det == 0f32 {
+	    return None; // seen edge on
+	}
 
-	// for when interpolating between the vertices, corresponds to the base
-	// let c0 = 1f32 - c1 - c2; 
+	let [z0, z1, z2] = [p0.z * shear_z, p1.z * shear_z, p2.z * shear_z];
+	let inverse_det = 1f32 / det;
+	let depth = (e0 * z0 + e1 * z1 + e2 * z2) * inverse_det;
+	if depth < ray.t_min || depth > ray.t_max {
+	    return None;
+	}
 
-	let hit_position_1 = ray.origin + ray.direction * depth;
-	let hit_position_2 = self.base + self.v1 * c1 + self.v2 * c2;
+	// the depth has to be positive by more than its rounding error, or the hit could be behind the origin
+	let max_x = p0.x.abs().max(p1.x.abs()).max(p2.x.abs());
+	let max_y = p0.y.abs().max(p1.y.abs()).max(p2.y.abs());
+	let max_z = z0.abs().max(z1.abs()).max(z2.abs());
+	let max_e = e0.abs().max(e1.abs()).max(e2.abs());
 
-	//assert!((hit_position_1 - hit_position_2).norm2() < 0.0001f32);
+	let delta_x = rounding_error(5) * (max_x + max_z);
+	let delta_y = rounding_error(5) * (max_y + max_z);
+	let delta_z = rounding_error(3) * max_z;
+	let delta_e = 2f32 * (rounding_error(2) * max_x * max_y + delta_y * max_x + delta_x * max_y);
+	let delta_depth = 3f32 * (rounding_error(3) * max_e * max_z + delta_e * max_z + delta_z * max_e) * inverse_det.abs();
+	if depth <= delta_depth {
+	    return None;
+	}
+
+	let [c0, c1, c2] = [e0 * inverse_det, e1 * inverse_det, e2 * inverse_det];
+	let [v0, v1, v2] = self.vertices;
+	let error = ((v0 * c0).abs() + (v1 * c1).abs() + (v2 * c2).abs()) * rounding_error(7);
+
+	Some((depth, [c0, c1, c2], error))
+    }
+
+}
+
+impl Shape for Triangle {
+    fn intersect(&self, ray: Ray) -> Option<(f32, SurfaceElement)> {
+	assert!(ray.direction.is_normal());
+
+	let (depth, barycentric, error) = self.intersect_barycentric(ray)?;
+	let [c0, c1, c2] = barycentric;
+
+	let normal = self.normal();
+	let (e1, e2) = self.edges();
+
+	// the shading normal is kept on the same side as the surface, the interpolation can tip it over at grazing angles
+	let shading_normal = match self.normals {
+	    Some([n0, n1, n2]) => {
+		let interpolated = n0 * c0 + n1 * c1 + n2 * c2;
+		if interpolated.norm2() == 0f32 {
+		    normal
+		} else if dot(interpolated, normal) < 0f32 {
+		    -interpolated.normalised()
+		} else {
+		    interpolated.normalised()
+		}
+	    },
+	    None => normal,
+	};
+
+	let texture_coordinates = match self.texture_coordinates {
+	    Some([t0, t1, t2]) => (
+		t0.0 * c0 + t1.0 * c1 + t2.0 * c2,
+		t0.1 * c0 + t1.1 * c1 + t2.1 * c2,
+	    ),
+	    None => (c1, c2),
+	};
+
+	// direction in which u increases, the first edge when the texture coordinates do not vary over the triangle
+	let tangent = match self.texture_coordinates {
+	    Some([t0, t1, t2]) => {
+		let (du1, dv1) = (t1.0 - t0.0, t1.1 - t0.1);
+		let (du2, dv2) = (t2.0 - t0.0, t2.1 - t0.1);
+		let det = du1 * dv2 - du2 * dv1;
+		let tangent = e1 * dv2 - e2 * dv1;
+		if det != 0f32 && tangent.norm2() > 0f32 {
+		    tangent * (1f32 / det)
+		} else {
+		    e1
+		}
+	    },
+	    None => e1,
+	}.normalised();
+
+	let color = self.colors.map(|[color0, color1, color2]| color0 * c0 + color1 * c1 + color2 * c2);
 
-	if (hit_position_1 - hit_position_2).norm2() > 0.0001f32 {
-	    println!("INCONSISTENT TRIANGLE INTERSECTION");
-	    println!("badness: {}", (hit_position_1 - hit_position_2).norm2());
-	    println!("{:#?}", self);
-	    println!("{:#?}", ray);
-	    println!("{}", depth);
-	    println!("{:#?}", hit_position_1);
-	    println!("{:#?}", hit_position_2);
-	}
-	
 	Some((
 	    depth,
 	    SurfaceElement {
-		position: hit_position_1,
-		normal:   normal,
+		position: self.point(barycentric),
+		error,
+		normal: shading_normal,
+		geometric_normal: normal,
+		tangent,
+		texture_coordinates,
+		color,
 		material: self.material,
 	    }))
     }
+
+    fn occludes(&self, ray: Ray, accept: &dyn Fn(Vector) -> bool) -> bool {
+	self.intersect_barycentric(ray).is_some() && accept(self.normal())
+    }
+
+    fn bounds(&self) -> BoundingBox {
+	let [p0, p1, p2] = self.vertices;
+	BoundingBox::empty()
+	    .include(p0)
+	    .include(p1)
+	    .include(p2)
+    }
+
+    fn area(&self) -> f32 {
+	let (e1, e2) = self.edges();
+	0.5f32 * cross(e1, e2).norm()
+    }
+
+    fn material(&self) -> usize {
+	self.material
+    }
+
+    fn sample_area(&self, p1: f32, p2: f32) -> ShapeSample {
+	let root = p1.sqrt();
+	let b1 = root * (1f32 - p2);
+	let b2 = root * p2;
+
+	ShapeSample {
+	    point: self.point([1f32 - b1 - b2, b1, b2]),
+	    normal: self.normal(),
+	    error: self.position_error(),
+	    pdf: 1f32 / self.area(),
+	}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+    use crate::transform::*;
+
+    use std::collections::BTreeMap;
+    use std::f32::consts::PI;
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    const MESHES: usize = 200;
+    const RAYS_PER_POINT: usize = 4;
+
+    // a bumpy grid of random size with jittered vertices and diagonals running either way, under a random transform
+    // that may mirror it, its faces are flatter than the rays fired at it, so that a ray aimed at an edge crosses it there
+    fn random_grid(rng: &mut StdRng) -> (RawModel, Transform) {
+	let (columns, rows) = (rng.gen_range(3 ..= 8), rng.gen_range(3 ..= 8));
+
+	let mut raw_model = RawModel::empty();
+	for i in 0 .. columns {
+	    for j in 0 .. rows {
+		raw_model.vertices.push(Vector {
+		    x: i as f32 + rng.gen_range(-0.2f32 .. 0.2f32),
+		    y: j as f32 + rng.gen_range(-0.2f32 .. 0.2f32),
+		    z: rng.gen_range(-0.1f32 .. 0.1f32),
+		});
+	    }
+	}
+	for i in 0 .. columns - 1 {
+	    for j in 0 .. rows - 1 {
+		let corner = |di: usize, dj: usize| (i + di) * rows + j + dj;
+		let triangles = if rng.gen() {
+		    [(corner(0, 0), corner(1, 0), corner(1, 1)), (corner(0, 0), corner(1, 1), corner(0, 1))]
+		} else {
+		    [(corner(0, 0), corner(1, 0), corner(0, 1)), (corner(1, 0), corner(1, 1), corner(0, 1))]
+		};
+		for indices in triangles {
+		    raw_model.triangle_indices.push(indices);
+		    raw_model.normal_indices.push(None);
+		    raw_model.texture_coordinate_indices.push(None);
+		}
+	    }
+	}
+
+	let mut factor = || if rng.gen() { 1f32 } else { -1f32 } * rng.gen_range(0.1f32 .. 10f32);
+	let scaling = Vector { x: factor(), y: factor(), z: factor() };
+	let offset = Vector { x: rng.gen_range(-1f32 .. 1f32), y: rng.gen_range(-1f32 .. 1f32), z: rng.gen_range(-1f32 .. 1f32) } * rng.gen_range(0f32 .. 100f32);
+	let transform = Transform::translation(offset)
+	    * Transform::rotation(0, rng.gen_range(0f32 .. 360f32))
+	    * Transform::rotation(1, rng.gen_range(0f32 .. 360f32))
+	    * Transform::rotation(2, rng.gen_range(0f32 .. 360f32))
+	    * Transform::scaling(scaling);
+	(raw_model, transform)
+    }
+
+    // the triangles on either side of each edge, by the indices of its vertices
+    fn edges(raw_model: &RawModel) -> BTreeMap<(usize, usize), Vec<usize>> {
+	let mut edges: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+	for (n, &(n1, n2, n3)) in raw_model.triangle_indices.iter().enumerate() {
+	    for (a, b) in [(n1, n2), (n2, n3), (n3, n1)] {
+		edges.entry((a.min(b), a.max(b))).or_default().push(n);
+	    }
+	}
+	edges
+    }
+
+    // a ray through the point, given before the transform, from either side of the grid and steeper than its faces
+    fn ray_through(rng: &mut StdRng, point: Vector, transform: Transform) -> Ray {
+	let angle = rng.gen_range(0f32 .. 2f32 * PI);
+	let horizontal = rng.gen_range(0f32 .. 1f32);
+	let vertical = if rng.gen() { 1f32 } else { -1f32 };
+	let offset = Vector { x: horizontal * angle.cos(), y: horizontal * angle.sin(), z: vertical } * rng.gen_range(0.5f32 .. 5f32);
+
+	let origin = transform.apply_point(point + offset);
+	Ray::new(origin, (transform.apply_point(point) - origin).normalised())
+    }
+
+    fn assert_barycentric(barycentric: [f32; 3]) {
+	let [c0, c1, c2] = barycentric;
+	assert!(c0 >= 0f32 && c1 >= 0f32 && c2 >= 0f32, "{:?} has negative barycentric coordinates", barycentric);
+	assert!((c0 + c1 + c2 - 1f32).abs() <= 4f32 * f32::EPSILON, "{:?} do not add up to one", barycentric);
+    }
+
+    // where of the vertices of the triangle the given one is
+    fn corner(triangle: &Triangle, vertex: Vector) -> Option<usize> {
+	triangle.vertices.iter().position(|&v| v.x == vertex.x && v.y == vertex.y && v.z == vertex.z)
+    }
+
+    // a ray through a shared edge hits one of the triangles on either side of it, or both when it runs exactly
+    // through the edge, in which case neither has any weight on the vertex that is not on the edge
+    #[test]
+    fn rays_through_shared_edges_hit_exactly_one_side() {
+	let mut rng = StdRng::seed_from_u64(22);
+	for _ in 0 .. MESHES {
+	    let (raw_model, transform) = random_grid(&mut rng);
+	    let edges = edges(&raw_model);
+	    let vertices = raw_model.vertices.clone();
+	    let model = Model::from_raw(raw_model, 0, transform, VertexNormals::Flat);
+
+	    for (&(a, b), sides) in edges.iter().filter(|(_, sides)| sides.len() == 2) {
+		let on_edge = [transform.apply_point(vertices[a]), transform.apply_point(vertices[b])];
+		for _ in 0 .. RAYS_PER_POINT {
+		    let t = rng.gen_range(0.01f32 .. 0.99f32);
+		    let ray = ray_through(&mut rng, vertices[a] * (1f32 - t) + vertices[b] * t, transform);
+
+		    let hits: Vec<(&Triangle, [f32; 3])> = sides.iter()
+			.map(|&n| &model.triangles[n])
+			.filter_map(|triangle| triangle.intersect_barycentric(ray).map(|(_, barycentric, _)| (triangle, barycentric)))
+			.collect();
+		    assert!(!hits.is_empty(), "{:?} slips through the edge between {:?}", ray, on_edge);
+
+		    for &(triangle, barycentric) in &hits {
+			assert_barycentric(barycentric);
+			if hits.len() == 2 {
+			    let off_edge = (0 .. 3).find(|&k| corner(triangle, on_edge[0]) != Some(k) && corner(triangle, on_edge[1]) != Some(k)).unwrap();
+			    assert_eq!(barycentric[off_edge], 0f32, "{:?} hits both sides of the edge between {:?}", ray, on_edge);
+			}
+		    }
+		}
+	    }
+	}
+    }
+
+    // the triangles around a vertex inside the grid leave no gap at the vertex
+    #[test]
+    fn rays_through_shared_vertices_hit() {
+	let mut rng = StdRng::seed_from_u64(23);
+	for _ in 0 .. MESHES {
+	    let (raw_model, transform) = random_grid(&mut rng);
+	    let edges = edges(&raw_model);
+	    let vertices = raw_model.vertices.clone();
+	    let model = Model::from_raw(raw_model, 0, transform, VertexNormals::Flat);
+
+	    let on_border: Vec<usize> = edges.iter().filter(|(_, sides)| sides.len() == 1).flat_map(|(&(a, b), _)| [a, b]).collect();
+	    for vertex in (0 .. vertices.len()).filter(|vertex| !on_border.contains(vertex)) {
+		let position = transform.apply_point(vertices[vertex]);
+		for _ in 0 .. RAYS_PER_POINT {
+		    let ray = ray_through(&mut rng, vertices[vertex], transform);
+		    let hits: Vec<[f32; 3]> = model.triangles.iter()
+			.filter(|triangle| corner(triangle, position).is_some())
+			.filter_map(|triangle| triangle.intersect_barycentric(ray).map(|(_, barycentric, _)| barycentric))
+			.collect();
+		    assert!(!hits.is_empty(), "{:?} slips through the vertex {:?}", ray, position);
+		    hits.into_iter().for_each(assert_barycentric);
+		}
+	    }
+	}
+    }
+
+    // the exact hit lies in the plane of the triangle, so the point found from the barycentric coordinates
+    // may not be further away from that plane than its error bound allows
+    #[test]
+    fn hit_points_are_within_their_error_bound() {
+	let mut rng = StdRng::seed_from_u64(24);
+	let wide = |v: Vector| [v.x as f64, v.y as f64, v.z as f64];
+	for _ in 0 .. MESHES {
+	    let (raw_model, transform) = random_grid(&mut rng);
+	    let edges = edges(&raw_model);
+	    let vertices = raw_model.vertices.clone();
+	    let model = Model::from_raw(raw_model, 0, transform, VertexNormals::Flat);
+
+	    for &(a, b) in edges.keys() {
+		let t = rng.gen_range(0f32 .. 1f32);
+		let ray = ray_through(&mut rng, vertices[a] * (1f32 - t) + vertices[b] * t, transform);
+		for triangle in &model.triangles {
+		    let (_, barycentric, error) = match triangle.intersect_barycentric(ray) {
+			Some(hit) => hit,
+			None => continue,
+		    };
+
+		    let [p0, p1, p2] = triangle.vertices.map(wide);
+		    let e1 = [p1[0] - p0[0], p1[1] - p0[1], p1[2] - p0[2]];
+		    let e2 = [p2[0] - p0[0], p2[1] - p0[1], p2[2] - p0[2]];
+		    let normal = [e1[1] * e2[2] - e1[2] * e2[1], e1[2] * e2[0] - e1[0] * e2[2], e1[0] * e2[1] - e1[1] * e2[0]];
+
+		    let position = wide(triangle.point(barycentric));
+		    let distance: f64 = (0 .. 3).map(|axis| normal[axis] * (position[axis] - p0[axis])).sum();
+		    let allowed: f64 = (0 .. 3).map(|axis| normal[axis].abs() * error.component(axis) as f64).sum();
+		    assert!(distance.abs() <= allowed, "{:?} is {} from the plane, allowed {}", position, distance.abs(), allowed);
+		}
+	    }
+	}
+    }
+
+    // the test this replaced: the hit with the plane of the triangle, expressed in its edges by solving the 2x2 system
+    // of their dot products, with the check that both ways of finding the point agree
+    fn plane_intersect(triangle: Triangle, ray: Ray) -> Option<(f32, [f32; 3])> {
+	let base = triangle.vertices[0];
+	let (v1, v2) = triangle.edges();
+	let normal = triangle.normal();
+
+	let cos_theta = dot(ray.direction, normal);
+	if cos_theta == 0f32 {
+	    return None;
+	}
+	let relative_origin = ray.origin - base;
+	let depth = -dot(relative_origin, normal) / cos_theta;
+	if depth < ray.t_min || depth > ray.t_max {
+	    return None;
+	}
+	let relative_intersection_point = relative_origin + ray.direction * depth;
+
+	let a1 = dot(relative_intersection_point, v1);
+	let a2 = dot(relative_intersection_point, v2);
+	let b11 = dot(v1, v1);
+	let b12 = dot(v1, v2);
+	let b22 = dot(v2, v2);
+	let det = b11 * b22 - b12 * b12;
+	let c1 = (a1 * b22 - a2 * b12) / det;
+	let c2 = (a2 * b11 - a1 * b12) / det;
+	if c1 < -0.0000001f32 || c2 < -0.0000001f32 || c1 + c2 > 1.0000001f32 {
+	    return None;
+	}
+
+	let hit_position_1 = ray.origin + ray.direction * depth;
+	let hit_position_2 = base + v1 * c1 + v2 * c2;
+	if (hit_position_1 - hit_position_2).norm2() > 0.0001f32 {
+	    return None;
+	}
+	Some((depth, [1f32 - c1 - c2, c1, c2]))
+    }
+
+    // triangles of random shape, size and place, with rays from random origins through a random point of each
+    fn random_rays(rng: &mut StdRng, n: usize) -> Vec<(Triangle, Ray)> {
+	let mut random_vector = |scale: f32| Vector { x: rng.gen_range(-scale .. scale), y: rng.gen_range(-scale .. scale), z: rng.gen_range(-scale .. scale) };
+	(0 .. n).map(|_| {
+	    let center = random_vector(50f32);
+	    let size = random_vector(1f32).norm() * 3f32;
+	    let triangle = Triangle {
+		vertices: [center + random_vector(size), center + random_vector(size), center + random_vector(size)],
+		normals: None,
+		texture_coordinates: None,
+		colors: None,
+		material: 0,
+	    };
+	    let [b1, b2] = [random_vector(1f32).x.abs(), random_vector(1f32).x.abs()];
+	    let (b1, b2) = if b1 + b2 > 1f32 { (1f32 - b1, 1f32 - b2) } else { (b1, b2) };
+	    let target = triangle.point([1f32 - b1 - b2, b1, b2]);
+	    let origin = target + random_vector(10f32);
+	    (triangle, Ray::new(origin, (target - origin).normalised()))
+	}).collect()
+    }
+
+    // both tests find the same hits away from the edges, where rounding can not tip the balance
+    #[test]
+    fn agrees_with_the_plane_test() {
+	let mut rng = StdRng::seed_from_u64(25);
+	for (triangle, ray) in random_rays(&mut rng, 100000) {
+	    // the plane test loses precision on slivers and at grazing angles
+	    let (v1, v2) = triangle.edges();
+	    let longest = v1.norm2().max(v2.norm2()).max((v2 - v1).norm2());
+	    if triangle.area() < 0.1f32 * longest || dot(triangle.normal(), ray.direction).abs() < 0.1f32 {
+		continue;
+	    }
+	    let (depth, barycentric) = match plane_intersect(triangle, ray) {
+		Some(hit) if hit.1.iter().all(|&c| c > 0.01f32) => hit,
+		_ => continue,
+	    };
+	    let (watertight_depth, watertight_barycentric, _) = triangle.intersect_barycentric(ray)
+		.unwrap_or_else(|| panic!("{:?} misses {:?}", ray, triangle));
+
+	    assert!((watertight_depth - depth).abs() <= 1e-4f32 * depth, "depth {} instead of {}", watertight_depth, depth);
+	    for (c, expected) in watertight_barycentric.iter().zip(barycentric.iter()) {
+		assert!((c - expected).abs() <= 1e-3f32, "{:?} instead of {:?}", watertight_barycentric, barycentric);
+	    }
+	}
+    }
+
+    // timing only means something in an optimised build: cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn faster_than_the_plane_test() {
+	let mut rng = StdRng::seed_from_u64(26);
+	let rays = random_rays(&mut rng, 1000000);
+
+	let time = |intersect: &dyn Fn(Triangle, Ray) -> bool| {
+	    (0 .. 5).map(|_| {
+		let start = Instant::now();
+		let hits = rays.iter().filter(|&&(triangle, ray)| intersect(black_box(triangle), black_box(ray))).count();
+		black_box(hits);
+		start.elapsed()
+	    }).min().unwrap()
+	};
+	let watertight = time(&|triangle, ray| triangle.intersect_barycentric(ray).is_some());
+	let plane = time(&|triangle, ray| plane_intersect(triangle, ray).is_some());
+
+	println!("watertight test {:?}, plane test {:?} for {} rays", watertight, plane, rays.len());
+	assert!(watertight < plane);
+    }
 }
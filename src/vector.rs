@@ -74,6 +74,24 @@ impl Vector {
 	norm2 < 1.0001f32 && norm2 > 0.9999f32
     }
 
+    pub fn abs(self) -> Vector {
+	Vector {
+	    x: self.x.abs(),
+	    y: self.y.abs(),
+	    z: self.z.abs(),
+	}
+    }
+
+    // component along the x (0), y (1) or z (2) axis
+    pub fn component(self, axis: usize) -> f32 {
+	match axis {
+	    0 => self.x,
+	    1 => self.y,
+	    2 => self.z,
+	    _ => panic!("invalid axis {}", axis),
+	}
+    }
+
     pub fn make_orthogonal_frame(self) -> (Vector, Vector) {
 	let normalised = self.normalised();
 	let vec_start = if normalised.x.abs() < 0.8f32 {